@@ -32,10 +32,10 @@ pub fn benchmark_async(c: &mut Criterion) {
                                 if random {
                                     // Read a random index.
                                     let read = shared_vector.read().await;
-                                    if read.len() > 0 {
+                                    if !read.is_empty() {
                                         let mut rng = rand::thread_rng();  
                                         let index = rng.gen_range(0..read.len());
-                                        black_box(assert_eq!(read[index], 5));
+                                        assert_eq!(black_box(read[index]), 5);
                                     }
                                 } else {
                                     // Add a new vector element.
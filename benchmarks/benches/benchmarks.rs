@@ -10,7 +10,10 @@ criterion_group!(
     name = benches;
     config = Criterion::default().measurement_time(Duration::new(10, 0)).sample_size(100);
     targets = mutex_benchmarks::benchmark_bfsharedmutex,
+        mutex_benchmarks::benchmark_write_latency,
+        mutex_benchmarks::benchmark_write_combined,
         mutex_benchmarks::benchmark_othermutexes,
+        mutex_benchmarks::benchmark_lock_api,
         async_benchmarks::benchmark_async,
         vec_benchmarks::benchmark_vector,
 );
@@ -2,9 +2,9 @@ use std::sync::{Arc, RwLock, Mutex};
 
 use criterion::Criterion;
 
-use bf_sharedmutex::BfSharedMutex;
+use bf_sharedmutex::{BfSharedMutex, RawBfRwLock};
 
-use benchmarks::{benchmark, NUM_ITERATIONS, READ_RATIOS, THREADS};
+use benchmarks::{benchmark, HANDLES, NUM_ITERATIONS, READ_RATIOS, THREADS};
 
 /// Benchmark the bfsharedmutex implementation
 pub fn benchmark_bfsharedmutex(c: &mut Criterion) {
@@ -30,6 +30,94 @@ pub fn benchmark_bfsharedmutex(c: &mut Criterion) {
     }
 }
 
+/// Benchmark the latency of an uncontended write for a growing number of idle handles, which the writer has to visit.
+pub fn benchmark_write_latency(c: &mut Criterion) {
+    for num_handles in HANDLES {
+        let shared = BfSharedMutex::new(());
+        let _handles: Vec<_> = (1..num_handles).map(|_| shared.clone()).collect();
+
+        c.bench_function(&format!("bf-sharedmutex::BfSharedMutex write latency {}", num_handles), |bencher| {
+            bencher.iter(|| {
+                let _guard = shared.write().unwrap();
+            });
+        });
+
+        // The same number of handles, but after the largest number of handles has been reached and dropped again.
+        let max_handles = HANDLES[HANDLES.len() - 1];
+        if num_handles == max_handles {
+            continue;
+        }
+
+        let shared = BfSharedMutex::new(());
+        let mut handles: Vec<_> = (1..max_handles).map(|_| shared.clone()).collect();
+        handles.drain(..max_handles - num_handles);
+
+        c.bench_function(&format!("bf-sharedmutex::BfSharedMutex write latency {} of {}", num_handles, max_handles), |bencher| {
+            bencher.iter(|| {
+                let _guard = shared.write().unwrap();
+            });
+        });
+    }
+}
+
+/// Benchmark short writes that are combined into a single exclusive section, compared to separate writes.
+pub fn benchmark_write_combined(c: &mut Criterion) {
+    for num_threads in THREADS {
+        benchmark(
+            c,
+            "bf-sharedmutex::BfSharedMutex write",
+            BfSharedMutex::new(0usize),
+            |_| {},
+            |shared| {
+                *shared.write().unwrap() += 1;
+            },
+            num_threads,
+            NUM_ITERATIONS,
+            1,
+        );
+
+        benchmark(
+            c,
+            "bf-sharedmutex::BfSharedMutex write_combined",
+            BfSharedMutex::new(0usize),
+            |_| {},
+            |shared| {
+                shared.write_combined(|number| *number += 1).unwrap();
+            },
+            num_threads,
+            NUM_ITERATIONS,
+            1,
+        );
+    }
+}
+
+/// Benchmark the implementations of lock_api::RawRwLock through the same generic lock_api::RwLock.
+pub fn benchmark_lock_api(c: &mut Criterion) {
+    benchmark_raw_rwlock::<RawBfRwLock>(c, "lock_api::RwLock<bf-sharedmutex::RawBfRwLock>");
+    benchmark_raw_rwlock::<parking_lot::RawRwLock>(c, "lock_api::RwLock<parking_lot::RawRwLock>");
+}
+
+fn benchmark_raw_rwlock<R: lock_api::RawRwLock + Send + Sync + 'static>(c: &mut Criterion, name: &str) {
+    for num_threads in THREADS {
+        for read_ratio in READ_RATIOS {
+            benchmark(
+                c,
+                name,
+                Arc::new(lock_api::RwLock::<R, ()>::new(())),
+                |shared| {
+                    let _guard = shared.read();
+                },
+                |shared| {
+                    let _guard = shared.write();
+                },
+                num_threads,
+                NUM_ITERATIONS,
+                read_ratio,
+            );
+        }
+    }
+}
+
 // Split up to first do our own benchmarks since than we can update the implementation easily.   
 pub fn benchmark_othermutexes(c: &mut Criterion) {
     for num_threads in THREADS {
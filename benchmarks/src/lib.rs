@@ -1,5 +1,4 @@
 use std::{
-    hint::black_box,
     sync::{atomic::AtomicBool, Arc, Barrier},
     thread::{self},
 };
@@ -12,8 +11,10 @@ use rand::{prelude::*, distributions::Bernoulli};
 pub const NUM_ITERATIONS: usize = 100000;
 pub const THREADS: [usize; 6] = [1, 2, 4, 8, 16, 20];
 pub const READ_RATIOS: [u32; 6] = [1, 10, 100, 1000, 10000, 100000];
+pub const HANDLES: [usize; 4] = [1, 4, 20, 64];
 
 /// Execute the benchmarks for a given readers-writer lock implementation.
+#[allow(clippy::too_many_arguments)]
 pub fn benchmark<T, R, W>(
     c: &mut Criterion,
     name: &str,
@@ -25,8 +26,8 @@ pub fn benchmark<T, R, W>(
     read_ratio: u32,
 ) where
     T: Clone + Send + 'static,
-    R: FnOnce(&T) -> () + Send + Copy + 'static,
-    W: FnOnce(&T) -> () + Send + Copy + 'static,
+    R: FnOnce(&T) + Send + Copy + 'static,
+    W: FnOnce(&T) + Send + Copy + 'static,
 {
     // Share threads to avoid overhead.
     let mut threads = vec![];
@@ -64,9 +65,9 @@ pub fn benchmark<T, R, W>(
                 // We execute it a fixed number of times.
                 for _ in 0..num_iterations {
                     if info.dist.sample(&mut rng) {
-                        black_box(write(&info.shared));
+                        write(&info.shared);
                     } else {
-                        black_box(read(&info.shared));
+                        read(&info.shared);
                     }
                 }
 
@@ -1,14 +1,65 @@
-use std::{
-    error::Error, fmt::Debug, ops::{Deref, DerefMut}, sync::{atomic::{fence, AtomicBool, Ordering}, Arc}
+use core::{
+    cell::Cell, fmt::Debug, mem::ManuallyDrop, ops::{Deref, DerefMut}, ptr::{self, NonNull}, sync::atomic::{AtomicBool, AtomicUsize, Ordering}, task::{Context, Poll}
 };
 
+use alloc::{sync::Arc, vec::Vec};
+
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+#[cfg(all(feature = "std", not(loom)))]
+use core::sync::atomic::AtomicPtr;
+
+#[cfg(all(not(loom), feature = "std"))]
+use std::sync::Mutex;
+
+#[cfg(all(not(loom), not(feature = "std")))]
+use spin::Mutex;
+
 #[cfg(not(loom))]
-use std::{sync::{Mutex, MutexGuard},  cell::UnsafeCell};
+use core::cell::UnsafeCell;
 
 #[cfg(loom)]
-use loom::{sync::{Mutex, MutexGuard}, cell::UnsafeCell};
-
-use crossbeam::utils::CachePadded;
+use loom::{sync::Mutex, cell::UnsafeCell};
+
+use crossbeam::utils::{Backoff, CachePadded};
+
+use crate::{BfSharedMutexError, LockResult, PoisonError, TryLockError, TryLockResult, waiter::{expired, lock, panicking, register, snooze, ExclusiveGuard, ExclusiveLock, Instant, Waiter}};
+
+#[cfg(all(feature = "std", not(loom)))]
+mod combine;
+mod deadlock;
+mod future;
+mod mapped;
+mod owned;
+#[cfg(all(feature = "lock_api", not(loom)))]
+mod raw;
+#[cfg(all(feature = "std", not(loom)))]
+mod rwlock;
+#[cfg(feature = "serde")]
+mod serialize;
+mod slab;
+mod stats;
+#[cfg(all(feature = "rayon", not(loom)))]
+mod worker;
+
+pub use self::future::*;
+pub use self::mapped::*;
+pub use self::owned::*;
+#[cfg(all(feature = "lock_api", not(loom)))]
+pub use self::raw::*;
+#[cfg(all(feature = "std", not(loom)))]
+pub use self::rwlock::*;
+#[cfg(feature = "stats")]
+pub use self::stats::{BfSharedMutexStats, LockStats};
+#[cfg(all(feature = "rayon", not(loom)))]
+pub use self::worker::*;
+
+#[cfg(all(feature = "std", not(loom)))]
+use self::combine::Request;
+use self::deadlock::{Access, LockId};
+use self::slab::ControlSlab;
+use self::stats::{Counters, Stopwatch};
 
 /// A shared mutex (readers-writer lock) implementation based on the so-called
 /// busy-forbidden protocol. Instead of a regular Mutex this class is Send and
@@ -17,12 +68,15 @@ use crossbeam::utils::CachePadded;
 /// the `read` operation and exclusive access for the `write` operation of the
 /// given object.
 pub struct BfSharedMutex<T> {
-    /// The local control bits of each instance. TODO: Maybe use pin to share the control bits among shared mutexes.
-    control: Arc<CachePadded<SharedMutexControl>>,
+    /// The local control bits of this instance, stored in the slab of the shared data which keeps them in place.
+    control: NonNull<CachePadded<SharedMutexControl>>,
 
-    /// Index into the `other` table.
+    /// The slot of the control bits in the slab.
     index: usize,
 
+    /// The number of nested reader sections of this instance, the busy flag is set iff it is non-zero.
+    depth: Cell<usize>,
+
     /// Information shared between all clones.
     shared: Arc<CachePadded<SharedData<T>>>,
 }
@@ -30,11 +84,40 @@ pub struct BfSharedMutex<T> {
 // Can only be send, but is not sync
 unsafe impl<T> Send for BfSharedMutex<T> {}
 
+/// Determines which side gets precedence when readers and writers compete for a [BfSharedMutex].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FairnessPolicy {
+    /// A writer only forbids access once it observes no active readers, a continuous stream of readers can starve it.
+    ReaderPreferring,
+
+    /// A writer forbids access immediately, and readers remain excluded until all queued writers have finished. A
+    /// continuous stream of writers can starve the readers, so it must be chosen with [BfSharedMutex::with_policy].
+    WriterPreferring,
+
+    /// Reader and writer phases alternate, the readers that were blocked by a writer enter before the next writer.
+    /// Otherwise a writer forbids access immediately, and the readers are allowed again when it has finished.
+    #[default]
+    PhaseFair,
+}
+
 /// The busy and forbidden flags used to implement the protocol.
 #[derive(Default)]
 struct SharedMutexControl {
     busy: AtomicBool,
     forbidden: AtomicBool,
+
+    /// Set while there are readers of this instance waiting until the forbidden flag is cleared.
+    waiting: AtomicBool,
+
+    /// The readers that wait until the forbidden flag is cleared, these are woken up by the writer.
+    waiters: Mutex<Vec<Waiter>>,
+
+    /// The statistics of this instance, only counted when the stats feature is enabled.
+    counters: Counters,
+
+    /// The write that this instance published for [BfSharedMutex::write_combined], or null.
+    #[cfg(all(feature = "std", not(loom)))]
+    combined: AtomicPtr<Request>,
 }
 
 struct SharedData<T> {
@@ -43,49 +126,351 @@ struct SharedData<T> {
     object: UnsafeCell<T>,
 
     /// The list of all the shared mutex instances.
-    other: Mutex<Vec<Option<Arc<CachePadded<SharedMutexControl>>>>>,
+    other: Mutex<Registry>,
+
+    /// Held by writers and the upgradable reader, ensures that at most one of them exists at any time.
+    upgrade: ExclusiveLock,
+
+    /// Set when a thread panicked while it had exclusive access to the object.
+    poisoned: AtomicBool,
+
+    /// The writer that waits until the readers have left their sections.
+    writer: Mutex<Option<Waiter>>,
+
+    /// The number of readers that were blocked by a writer and have not yet entered their reader section.
+    blocked: AtomicUsize,
+
+    /// Set while the holder of the upgrade lock waits until the fairness policy allows it to forbid access, the readers
+    /// then wake it up when they leave their section or stop being blocked.
+    pending: AtomicBool,
+
+    policy: FairnessPolicy,
+
+    /// Identifies the shared mutex for the deadlock detector, only used when the deadlock-detection feature is enabled.
+    id: LockId,
+}
+
+/// The control bits of all the shared mutex instances.
+#[derive(Default)]
+struct Registry {
+    /// The slots of dropped instances are reused, the writers only visit the slots in use.
+    controls: ControlSlab,
+
+    /// Set while a writer has forbidden access, new instances then start out as forbidden as well. A writer-preferring
+    /// writer leaves the instances forbidden for the next queued writer.
+    forbidden: bool,
+
+    /// Set while the holder of the upgrade lock is writing, or waiting for the readers to leave.
+    writing: bool,
+
+    /// The number of writers that are waiting for the upgrade lock.
+    queued: usize,
+
+    /// The statistics of the instances that have been dropped.
+    #[cfg(feature = "stats")]
+    retired: LockStats,
+}
+
+impl Registry {
+
+    /// Sets the forbidden flag of all instances.
+    fn forbid_all(&mut self) {
+        for control in self.controls.iter() {
+            debug_assert!(!control.forbidden.load(core::sync::atomic::Ordering::SeqCst), 
+                "Other instance is already forbidden, this cannot happen");
+
+            control.forbidden.store(true, core::sync::atomic::Ordering::SeqCst);
+        }
+
+        self.forbidden = true;
+    }
+
+    /// Clears the forbidden flag of all instances and wakes up the waiting readers.
+    fn allow_all(&mut self) {
+        self.forbidden = false;
+
+        for control in self.controls.iter() {
+            control.forbidden.store(false, core::sync::atomic::Ordering::SeqCst);
+
+            if control.waiting.load(Ordering::SeqCst) {
+                let mut waiters = lock(&control.waiters);
+                control.waiting.store(false, Ordering::SeqCst);
+
+                for waiter in waiters.drain(..) {
+                    waiter.wake();
+                }
+            }
+        }
+    }
+
+    /// Returns true iff any of the instances is inside a reader section.
+    fn any_busy(&self) -> bool {
+        self.controls.iter().any(|control| control.busy.load(Ordering::SeqCst))
+    }
+
+    /// Starts writing, the instances can already be forbidden when the previous writer handed them over.
+    fn forbid(&mut self) {
+        self.writing = true;
+        if !self.forbidden {
+            self.forbid_all();
+        }
+    }
+
+    /// Stops writing, the instances remain forbidden when a writer-preferring lock has writers queued.
+    fn release(&mut self, policy: FairnessPolicy) {
+        self.writing = false;
+        if policy != FairnessPolicy::WriterPreferring || self.queued == 0 {
+            self.allow_all();
+        }
+    }
+
+    /// Removes a writer from the queue, the readers are allowed again when it was the last one that was handed over
+    /// the forbidden instances but it did not acquire the upgrade lock.
+    fn dequeue(&mut self, acquired: bool) {
+        self.queued -= 1;
+        if !acquired && self.queued == 0 && !self.writing && self.forbidden {
+            self.allow_all();
+        }
+    }
+}
+
+impl<T> SharedData<T> {
+
+    /// Acquires the upgrade lock as a writer, which queues the writer so that a writer-preferring lock hands over the
+    /// forbidden instances. Returns None when the deadline passed first.
+    fn lock_upgrade(&self, deadline: Option<Instant>) -> Option<ExclusiveGuard<'_>> {
+        lock(&self.other).queued += 1;
+        let upgrade = self.upgrade.lock(deadline);
+        lock(&self.other).dequeue(upgrade.is_some());
+        upgrade
+    }
+
+    /// Returns true iff the fairness policy allows the holder of the upgrade lock to forbid access now.
+    fn may_forbid(&self) -> bool {
+        match self.policy {
+            FairnessPolicy::ReaderPreferring => !lock(&self.other).any_busy(),
+            FairnessPolicy::WriterPreferring => true,
+            FairnessPolicy::PhaseFair => self.blocked.load(Ordering::SeqCst) == 0,
+        }
+    }
+
+    /// Forbids all instances from entering a reader section and waits until they have left, the caller must hold the
+    /// upgrade lock. Returns false, after allowing all instances again, when the deadline passed first.
+    fn forbid_and_wait(&self, counters: &Counters, deadline: Option<Instant>) -> bool {
+        // Wait until the policy allows us to start, for example when the blocked readers of the last phase have entered.
+        let backoff = Backoff::new();
+        let mut registered = false;
+        while !self.may_forbid() {
+            if expired(deadline) {
+                self.clear_pending();
+                lock(&self.other).release(self.policy);
+                return false;
+            }
+
+            // Register before checking the policy again, so that the readers are guaranteed to wake us up.
+            snooze(&backoff, &mut registered, deadline, |waiter| self.register_pending(waiter));
+        }
+
+        self.clear_pending();
+
+        // Make all instances wait due to forbidden access.
+        lock(&self.other).forbid();
+
+        // Wait for the instances to exit their busy status, or roll back the forbidden flags when we run out of time.
+        if !self.wait_for_readers(counters, deadline) {
+            lock(&self.other).release(self.policy);
+            return false;
+        }
+
+        true
+    }
+
+    /// Waits until all instances have left their reader sections, returns false when the deadline passed first. The
+    /// writer spins for a short while, and then parks until it is woken up by a reader that clears its busy flag.
+    ///
+    /// The registry is not locked while waiting, so that instances can be cloned, dropped and inspected in the meantime.
+    /// The forbidden instances cannot enter a new reader section, so the writer is done once a single scan of the
+    /// registry finds no busy instance.
+    fn wait_for_readers(&self, counters: &Counters, deadline: Option<Instant>) -> bool {
+        let mut parked = false;
+        let mut result = true;
+
+        'outer: while let Some(control) = self.find_busy() {
+            // The slab keeps the control block in place, even when its instance is dropped in the meantime.
+            let control = unsafe { control.as_ref() };
+            let backoff = Backoff::new();
+            let mut registered = false;
+
+            while control.busy.load(Ordering::SeqCst) {
+                counters.writer_spin();
+                if expired(deadline) {
+                    result = false;
+                    break 'outer;
+                }
+
+                // Register before checking the busy flag again, so that the reader is guaranteed to wake us up.
+                snooze(&backoff, &mut registered, deadline, |waiter| {
+                    *lock(&self.writer) = Some(waiter);
+                    parked = true;
+                });
+            }
+        }
+
+        if parked {
+            *lock(&self.writer) = None;
+        }
+
+        result
+    }
+
+    /// Returns the control block of an instance that is inside a reader section, if any.
+    fn find_busy(&self) -> Option<NonNull<CachePadded<SharedMutexControl>>> {
+        lock(&self.other).controls.iter().find(|control| control.busy.load(Ordering::SeqCst)).map(NonNull::from)
+    }
+
+    /// Registers the writer that waits until the fairness policy allows it to forbid access.
+    fn register_pending(&self, waiter: Waiter) {
+        *lock(&self.writer) = Some(waiter);
+        self.pending.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops waiting for the fairness policy, the caller must hold the upgrade lock.
+    fn clear_pending(&self) {
+        if self.pending.swap(false, Ordering::SeqCst) {
+            *lock(&self.writer) = None;
+        }
+    }
+
+    /// Wakes up the writer that waits for the readers, or for the fairness policy.
+    fn wake_writer(&self) {
+        if let Some(writer) = lock(&self.writer).as_ref() {
+            writer.wake();
+        }
+    }
+
+    /// Removes a reader that was blocked by a writer, the last one wakes up a writer that waits for the policy.
+    fn unblock(&self) {
+        if self.blocked.fetch_sub(1, Ordering::SeqCst) == 1 && self.pending.load(Ordering::SeqCst) {
+            self.wake_writer();
+        }
+    }
+
+    /// Checks whether all instances have left their reader sections, otherwise the task is woken up by the reader
+    /// that clears its busy flag.
+    fn poll_readers(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let other = lock(&self.other);
+
+        if other.any_busy() {
+            // Register before checking the busy flags again, so that the reader is guaranteed to wake us up.
+            *lock(&self.writer) = Some(Waiter::Task(cx.waker().clone()));
+
+            if other.any_busy() {
+                return Poll::Pending;
+            }
+        }
+
+        *lock(&self.writer) = None;
+        Poll::Ready(())
+    }
 }
 
 impl<T> BfSharedMutex<T> {
 
     /// Constructs a new shared mutex for protecting access to the given object.
     pub fn new(object: T) -> Self {
-        let control = Arc::new(CachePadded::new(SharedMutexControl::default()));
+        Self::with_policy(object, FairnessPolicy::default())
+    }
+
+    /// Constructs a new shared mutex that resolves the competition between readers and writers with the given policy.
+    pub fn with_policy(object: T, policy: FairnessPolicy) -> Self {
+        Self::with_slab(object, policy, ControlSlab::default())
+    }
+
+    /// Constructs a new shared mutex that preallocates room for the given number of instances, including this one.
+    /// Cloning then never allocates, but [BfSharedMutex::try_clone] fails once all instances are in use.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `max_handles` is zero.
+    pub fn with_max_handles(object: T, max_handles: usize) -> Self {
+        assert!(max_handles > 0, "The shared mutex needs room for at least one instance");
+        Self::with_slab(object, FairnessPolicy::default(), ControlSlab::with_capacity(max_handles))
+    }
+
+    fn with_slab(object: T, policy: FairnessPolicy, mut controls: ControlSlab) -> Self {
+        let (index, control) = controls.insert().expect("The slab has room for the first instance");
+
+        // With a fixed capacity every instance can wait for the upgrade lock without allocating.
+        let waiters = controls.fixed_capacity().unwrap_or(0);
 
         Self {
-            control: control.clone(),
+            control,
+            index,
             shared: Arc::new(CachePadded::new(SharedData {
                 object: UnsafeCell::new(object),
-                other: Mutex::new(vec![Some(control.clone())]),
+                other: Mutex::new(Registry {
+                    controls,
+                    ..Registry::default()
+                }),
+                upgrade: ExclusiveLock::with_capacity(waiters),
+                poisoned: AtomicBool::new(false),
+                writer: Mutex::new(None),
+                blocked: AtomicUsize::new(0),
+                pending: AtomicBool::new(false),
+                policy,
+                id: LockId::default(),
             })),
-            index: 0,
+            depth: Cell::new(0),
         }
     }
 }
 
-impl<T> Clone for BfSharedMutex<T> {
-    fn clone(&self) -> Self {
+impl<T> BfSharedMutex<T> {
+
+    /// Creates another instance like [Clone::clone], but fails with [BfSharedMutexError::RegistryFull] when the
+    /// shared mutex was constructed by [BfSharedMutex::with_max_handles] and all instances are in use.
+    pub fn try_clone(&self) -> Result<Self, BfSharedMutexError> {
 
         // Register a new instance in the other list.
-        let control = Arc::new(CachePadded::new(SharedMutexControl::default()));
+        let mut other = lock(&self.shared.other);
+        let (index, control) = other.controls.insert().ok_or(BfSharedMutexError::RegistryFull)?;
 
-        let mut other = self.shared.other.lock().expect("Failed to lock mutex");
-        other.push(Some(control.clone()));
+        // The slot cannot be used by any other instance, and the slab keeps it in place.
+        unsafe { control.as_ref() }.forbidden.store(other.forbidden, Ordering::SeqCst);
 
-        Self {
+        Ok(Self {
             control,
-            index: other.len() - 1,
+            index,
+            depth: Cell::new(0),
             shared: self.shared.clone(),
-        }
+        })
+    }
+}
+
+impl<T> Clone for BfSharedMutex<T> {
+    /// # Panics
+    ///
+    /// Panics when all instances of a shared mutex with a fixed capacity are in use, see [BfSharedMutex::try_clone].
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Cannot clone the shared mutex")
     }
 }
 
 impl<T> Drop for BfSharedMutex<T> {
     fn drop(&mut self) {
-        let mut other = self.shared.other.lock().expect("Failed to lock mutex");
-
-        // Remove ourselves from the table.
-        other[self.index] = None;
+        let mut other = lock(&self.shared.other);
+
+        // Free our slot so that the next instance can reuse it, writers then see an idle instance. The busy flag can
+        // still be set when a read guard was forgotten, but no reference to the object can outlive this instance. A
+        // writer can be waiting for that flag without holding the registry, so it is woken up as well.
+        self.clear_busy();
+        self.control().forbidden.store(false, Ordering::SeqCst);
+        self.shared.id.released(self.index);
+        #[cfg(feature = "stats")]
+        {
+            other.retired += self.control().counters.take();
+        }
+        other.controls.remove(self.index);
     }
 }
 
@@ -93,7 +478,10 @@ impl<T> Drop for BfSharedMutex<T> {
 #[must_use = "Dropping the guard unlocks the shared mutex immediately"]
 pub struct BfSharedMutexWriteGuard<'a, T> {
     mutex: &'a BfSharedMutex<T>,
-    guard: MutexGuard<'a, Vec<Option<Arc<CachePadded<SharedMutexControl>>>>>,
+    _upgrade: ExclusiveGuard<'a>,
+
+    /// Whether the thread was already panicking when the guard was acquired.
+    panicking: bool,
 
     #[cfg(loom)]
     access: loom::cell::MutPtr<T>,
@@ -136,15 +524,31 @@ impl<'a, T> DerefMut for BfSharedMutexWriteGuard<'a, T> {
     }
 }
 
-impl<'a, T> Drop for BfSharedMutexWriteGuard<'a, T> {
-    fn drop(&mut self) {
+impl<'a, T> BfSharedMutexWriteGuard<'a, T> {
 
-        // Allow other threads to acquire access to the shared mutex.
-        for control in self.guard.iter().flatten() {
-            control.forbidden.store(false, std::sync::atomic::Ordering::SeqCst);
+    /// Downgrades to shared access, no other writer can acquire the shared mutex in between.
+    pub fn downgrade(self) -> BfSharedMutexReadGuard<'a, T> {
+        let mut this = ManuallyDrop::new(self);
+        let mutex = this.mutex;
+
+        mutex.downgrade_writer();
+
+        // Release the upgrade lock without executing the drop of the write guard.
+        unsafe {
+            ptr::drop_in_place(&mut this._upgrade);
+            #[cfg(loom)]
+            ptr::drop_in_place(&mut this.access);
         }
 
-        // The mutex guard is then dropped here.
+        mutex.read_guard()
+    }
+}
+
+impl<'a, T> Drop for BfSharedMutexWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.exit_writer(self.panicking);
+
+        // The upgrade lock is then released here.
     }
 }
 
@@ -177,127 +581,527 @@ impl<'a, T> Deref for BfSharedMutexReadGuard<'a, T> {
 
 impl<'a, T> Drop for BfSharedMutexReadGuard<'a, T> {
     fn drop(&mut self) {
-        debug_assert!(self.mutex.control.busy.load(Ordering::SeqCst), "Cannot unlock shared lock that was not acquired");
+        self.mutex.exit_reader();
+    }
+}
+
+/// The guard object for shared access to the underlying object that can be upgraded to exclusive access.
+#[must_use = "Dropping the guard unlocks the shared mutex immediately"]
+pub struct BfSharedMutexUpgradableReadGuard<'a, T> {
+    mutex: &'a BfSharedMutex<T>,
+    upgrade: ExclusiveGuard<'a>,
+
+    #[cfg(loom)]
+    access: loom::cell::ConstPtr<T>,
+}
+
+impl<'a, T> BfSharedMutexUpgradableReadGuard<'a, T> {
+
+    /// Upgrades to exclusive access, no other writer can acquire the shared mutex in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics when other read guards of this instance exist, since these would still access the object.
+    pub fn upgrade(self) -> LockResult<BfSharedMutexWriteGuard<'a, T>> {
+        // Checked while the guard is intact, so that it still leaves the reader section when this panics.
+        assert_eq!(self.mutex.depth.get(), 1, "Cannot upgrade while other read guards of this instance exist");
+
+        let this = ManuallyDrop::new(self);
+        let mutex = this.mutex;
+
+        // Take the fields out of the guard, since its drop would release our access.
+        let upgrade = unsafe { ptr::read(&this.upgrade) };
+        #[cfg(loom)]
+        drop(unsafe { ptr::read(&this.access) });
+
+        mutex.upgrade_reader();
+        mutex.poison(mutex.write_guard(upgrade))
+    }
+}
+
+#[cfg(not(loom))]
+impl<'a, T> Deref for BfSharedMutexUpgradableReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Writers are excluded by the upgrade mutex, so there can only be immutable references to the object.
+        unsafe { &*self.mutex.shared.object.get() }
+    }
+}
+
+#[cfg(loom)]
+impl<'a, T> Deref for BfSharedMutexUpgradableReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.access.deref() }
+    }
+}
+
+impl<'a, T> Drop for BfSharedMutexUpgradableReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.exit_reader();
 
-        self.mutex.control.busy.store(false, Ordering::SeqCst);
+        // The upgrade lock is then released here.
     }
 }
 
 impl<T> BfSharedMutex<T> {
 
-    /// Provides read access to the underlying object, allowing multiple immutable references to it.
+    /// Provides read access to the underlying object, allowing multiple immutable references to it. The reader
+    /// sections of a single instance can be nested, writers wait until the outermost section has ended.
     #[inline]
-    pub fn read<'a>(&'a self) -> Result<BfSharedMutexReadGuard<'a, T>, Box<dyn Error + 'a>> {
-        debug_assert!(!self.control.busy.load(Ordering::SeqCst), "Cannot acquire read access again inside a reader section");
-
-        self.control.busy.store(true, Ordering::SeqCst);
-        #[cfg(loom)]
-        fence(Ordering::SeqCst);
-        while self.control.forbidden.load(Ordering::SeqCst) {
-            self.control.busy.store(false, Ordering::SeqCst);
-    
-            // Wait for the mutex of the writer.
-            let mut _guard = self.shared.other.lock()?;
-            
-            self.control.busy.store(true, Ordering::SeqCst);
+    pub fn read<'a>(&'a self) -> LockResult<BfSharedMutexReadGuard<'a, T>> {
+        self.shared.id.check(Access::Read);
+
+        // Inside a nested section the busy flag is already set, which is enough to keep writers out.
+        if self.depth.get() == 0 {
+            self.control().busy.store(true, Ordering::SeqCst);
+            #[cfg(loom)]
+            core::sync::atomic::fence(Ordering::SeqCst);
+            if self.control().forbidden.load(Ordering::SeqCst) {
+                let stopwatch = Stopwatch::start();
+                self.control().counters.forbidden_read();
+                self.shared.blocked.fetch_add(1, Ordering::SeqCst);
+                while self.control().forbidden.load(Ordering::SeqCst) {
+                    self.clear_busy();
+
+                    // Wait for the writer to allow us again, without going through the mutex of the writer.
+                    self.wait_for_writer(None);
+
+                    self.control().busy.store(true, Ordering::SeqCst);
+                }
+                self.shared.unblock();
+                self.control().counters.waited(stopwatch);
+            }
         }
 
         // We now have immutable access to the object due to the protocol.
-        #[cfg(loom)]
-        return Ok(BfSharedMutexReadGuard {
-            mutex: self,
-            access: self.shared.object.get(),
-        });
+        self.poison(self.read_guard())
+    }
 
-        #[cfg(not(loom))]
-        Ok(BfSharedMutexReadGuard {
-            mutex: self,
-        })
+    /// Attempts to acquire read access without blocking, fails with [TryLockError::WouldBlock] when a writer is active.
+    #[inline]
+    pub fn try_read<'a>(&'a self) -> TryLockResult<BfSharedMutexReadGuard<'a, T>> {
+        if self.depth.get() == 0 {
+            self.control().busy.store(true, Ordering::SeqCst);
+            #[cfg(loom)]
+            core::sync::atomic::fence(Ordering::SeqCst);
+            if self.control().forbidden.load(Ordering::SeqCst) {
+                // A writer is active, leave the busy flag cleared so that it can continue.
+                self.clear_busy();
+                return Err(TryLockError::WouldBlock);
+            }
+        }
+
+        Ok(self.poison(self.read_guard())?)
     }
 
     /// Provide write access to the underlying object, only a single mutable reference to the object exists.
     #[inline]
-    pub fn write<'a>(&'a self) -> Result<BfSharedMutexWriteGuard<'a, T>, Box<dyn Error + 'a>> {
+    pub fn write<'a>(&'a self) -> LockResult<BfSharedMutexWriteGuard<'a, T>> {
+        self.shared.id.check(Access::Write);
 
-        let other = self.shared.other.lock()?;
+        let stopwatch = Stopwatch::start();
+        let upgrade = self.shared.lock_upgrade(None).expect("Cannot time out without a deadline");
 
-        debug_assert!(!self.control.busy.load(std::sync::atomic::Ordering::SeqCst), 
+        debug_assert!(!self.control().busy.load(core::sync::atomic::Ordering::SeqCst), 
             "Can only exclusive lock outside of a shared lock, no upgrading!");
-        debug_assert!(!self.control.forbidden.load(std::sync::atomic::Ordering::SeqCst), 
-            "Can not acquire exclusive lock inside of exclusive section");
 
-        // Make all instances wait due to forbidden access.
-        for control in other.iter().flatten() {
-            debug_assert!(!control.forbidden.load(std::sync::atomic::Ordering::SeqCst), 
-                "Other instance is already forbidden, this cannot happen");
+        // Wait for the instances to exit their busy status, our own busy flag is cleared so it can be included.
+        self.shared.forbid_and_wait(&self.control().counters, None);
+        self.control().counters.waited(stopwatch);
 
-            control.forbidden.store(true, std::sync::atomic::Ordering::SeqCst);
+        // We now have exclusive access to the object according to the protocol
+        self.poison(self.write_guard(upgrade))
+    }
+
+    /// Attempts to acquire write access without blocking, fails with [TryLockError::WouldBlock] when another
+    /// writer holds the lock or any of the other instances is inside a reader section.
+    #[inline]
+    pub fn try_write<'a>(&'a self) -> TryLockResult<BfSharedMutexWriteGuard<'a, T>> {
+
+        let upgrade = self.shared.upgrade.try_lock().ok_or(TryLockError::WouldBlock)?;
+
+        // The registry is only held briefly, for example by a thread that clones its instance, so we wait for it.
+        let mut other = lock(&self.shared.other);
+
+        debug_assert!(!self.control().busy.load(core::sync::atomic::Ordering::SeqCst), 
+            "Can only exclusive lock outside of a shared lock, no upgrading!");
+
+        other.forbid();
+
+        // Readers that are still busy would make us wait, so undo the forbidden flags instead.
+        if other.any_busy() {
+            other.release(self.shared.policy);
+            return Err(TryLockError::WouldBlock);
+        }
+
+        drop(other);
+        Ok(self.poison(self.write_guard(upgrade))?)
+    }
+
+    /// Provides read access like [BfSharedMutex::read], but gives up after the given timeout has elapsed.
+    #[cfg(feature = "std")]
+    pub fn read_for<'a>(&'a self, timeout: Duration) -> TryLockResult<BfSharedMutexReadGuard<'a, T>> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.read_until(deadline),
+            None => Ok(self.read()?),
         }
+    }
 
-        // Wait for the instances to exit their busy status.
-        for (index, option) in other.iter().enumerate() {
-            if index != self.index {
+    /// Provides read access like [BfSharedMutex::read], but gives up once the deadline has passed.
+    #[cfg(feature = "std")]
+    pub fn read_until<'a>(&'a self, deadline: Instant) -> TryLockResult<BfSharedMutexReadGuard<'a, T>> {
+        if self.depth.get() == 0 {
+            self.control().busy.store(true, Ordering::SeqCst);
+            #[cfg(loom)]
+            core::sync::atomic::fence(Ordering::SeqCst);
+            if self.control().forbidden.load(Ordering::SeqCst) {
+                let stopwatch = Stopwatch::start();
+                self.control().counters.forbidden_read();
+                self.shared.blocked.fetch_add(1, Ordering::SeqCst);
+                while self.control().forbidden.load(Ordering::SeqCst) {
+                    self.clear_busy();
+
+                    // Wait for the writer, the busy flag is cleared so nothing has to be undone on a timeout.
+                    let acquired = self.wait_for_writer(Some(deadline));
+                    if !acquired {
+                        self.shared.unblock();
+                        self.control().counters.waited(stopwatch);
+                        return Err(TryLockError::TimedOut);
+                    }
 
-                if let Some(object) = option {
-                    while object.busy.load(std::sync::atomic::Ordering::SeqCst) { std::hint::spin_loop(); }
+                    self.control().busy.store(true, Ordering::SeqCst);
                 }
-            }            
+                self.shared.unblock();
+                self.control().counters.waited(stopwatch);
+            }
         }
 
-        // We now have exclusive access to the object according to the protocol
+        Ok(self.poison(self.read_guard())?)
+    }
+
+    /// Provides write access like [BfSharedMutex::write], but gives up after the given timeout has elapsed.
+    #[cfg(feature = "std")]
+    pub fn write_for<'a>(&'a self, timeout: Duration) -> TryLockResult<BfSharedMutexWriteGuard<'a, T>> {
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.write_until(deadline),
+            None => Ok(self.write()?),
+        }
+    }
+
+    /// Provides write access like [BfSharedMutex::write], but gives up once the deadline has passed.
+    #[cfg(feature = "std")]
+    pub fn write_until<'a>(&'a self, deadline: Instant) -> TryLockResult<BfSharedMutexWriteGuard<'a, T>> {
+
+        let stopwatch = Stopwatch::start();
+        let upgrade = self.shared.lock_upgrade(Some(deadline));
+        let acquired = upgrade.is_some() && self.shared.forbid_and_wait(&self.control().counters, Some(deadline));
+        self.control().counters.waited(stopwatch);
+
+        let upgrade = upgrade.filter(|_| acquired).ok_or(TryLockError::TimedOut)?;
+
+        debug_assert!(!self.control().busy.load(core::sync::atomic::Ordering::SeqCst), 
+            "Can only exclusive lock outside of a shared lock, no upgrading!");
+
+        Ok(self.poison(self.write_guard(upgrade))?)
+    }
+
+    /// Provides read access that can later be upgraded to write access without releasing it in between. These
+    /// guards coexist with the guards of [BfSharedMutex::read], but exclude writers and other upgradable readers.
+    ///
+    /// # Panics
+    ///
+    /// Panics when this instance is already inside a reader section, since the upgrade would have to wait for itself.
+    pub fn upgradable_read<'a>(&'a self) -> LockResult<BfSharedMutexUpgradableReadGuard<'a, T>> {
+        assert_eq!(self.depth.get(), 0, "Cannot acquire upgradable read access inside a reader section of this instance");
+
+        self.shared.id.check(Access::Read);
+        let upgrade = self.shared.upgrade.lock(None).expect("Cannot time out without a deadline");
+        self.upgradable_guard(upgrade)
+    }
+
+    /// Attempts to acquire upgradable read access without blocking, fails with [TryLockError::WouldBlock] when a
+    /// writer or another upgradable reader holds the shared mutex.
+    ///
+    /// # Panics
+    ///
+    /// Panics when this instance is already inside a reader section, like [BfSharedMutex::upgradable_read].
+    pub fn try_upgradable_read<'a>(&'a self) -> TryLockResult<BfSharedMutexUpgradableReadGuard<'a, T>> {
+        assert_eq!(self.depth.get(), 0, "Cannot acquire upgradable read access inside a reader section of this instance");
+
+        let upgrade = self.shared.upgrade.try_lock().ok_or(TryLockError::WouldBlock)?;
+        Ok(self.upgradable_guard(upgrade)?)
+    }
+
+    /// Enters the reader section of an upgradable reader, the caller must hold the upgrade lock and this instance must
+    /// not be inside a reader section.
+    fn upgradable_guard<'a>(&'a self, upgrade: ExclusiveGuard<'a>) -> LockResult<BfSharedMutexUpgradableReadGuard<'a, T>> {
+        // Writers also hold the upgrade lock, so no writer is active even when a queued writer was handed the forbidden flags.
+        self.control().busy.store(true, Ordering::SeqCst);
+        self.depth.set(1);
+        self.control().counters.read();
+        self.shared.id.held(self.index, Access::Read);
+
         #[cfg(loom)]
-        return Ok(BfSharedMutexWriteGuard {
+        return self.poison(BfSharedMutexUpgradableReadGuard {
             mutex: self,
-            guard: other,
-            access: self.shared.object.get_mut(),
+            upgrade,
+            access: self.shared.object.get(),
         });
 
         #[cfg(not(loom))]
-        Ok(BfSharedMutexWriteGuard {
+        self.poison(BfSharedMutexUpgradableReadGuard {
             mutex: self,
-            guard: other,
+            upgrade,
         })
     }
 
-    /// Obtain mutable access to the object without locking, is safe because we have mutable access.
-    #[cfg(not(loom))]
-    pub fn get_mut(&mut self) -> &mut T {
-        unsafe {
-            &mut *self.shared.object.get()
+    /// Returns the control bits of this instance.
+    fn control(&self) -> &SharedMutexControl {
+        // The shared data keeps the slab alive, and the slot is not reused before this instance is dropped.
+        unsafe { self.control.as_ref() }
+    }
+
+    /// Leaves a (nested) reader section, the busy flag is only cleared when the outermost section ends.
+    fn exit_reader(&self) {
+        let depth = self.depth.get();
+        debug_assert!(depth > 0 && self.control().busy.load(Ordering::SeqCst), "Cannot unlock shared lock that was not acquired");
+
+        self.depth.set(depth - 1);
+        if depth == 1 {
+            self.shared.id.released(self.index);
+            self.clear_busy();
         }
     }
-}
 
-impl<T: Debug> Debug for BfSharedMutex<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        
-        f.debug_map().entry(&"busy", &self.control.busy.load(Ordering::SeqCst))
-        .entry(&"forbidden", &self.control.forbidden.load(Ordering::SeqCst))
-        .entry(&"index", &self.index)
-        .entry(&"len(other)", &self.shared.other.lock().unwrap().len())
-        .finish()?;
+    /// Waits until the writer has cleared our forbidden flag, returns false when the deadline passed first. The reader
+    /// spins for a short while, and then parks until it is woken up by the writer, so that all readers resume in parallel.
+    fn wait_for_writer(&self, deadline: Option<Instant>) -> bool {
+        let backoff = Backoff::new();
+        let mut registered = false;
 
-        writeln!(f)?;
-        writeln!(f, "other values: [")?;
-        for control in self.shared.other.lock().unwrap().iter().flatten() {
-            f.debug_map().entry(&"busy", &control.busy.load(Ordering::SeqCst))
-            .entry(&"forbidden", &control.forbidden.load(Ordering::SeqCst))
-            .finish()?;
-            writeln!(f)?;
+        while self.control().forbidden.load(Ordering::SeqCst) {
+            if expired(deadline) {
+                return false;
+            }
+
+            // Register before checking the forbidden flag again, so that the writer is guaranteed to wake us up.
+            snooze(&backoff, &mut registered, deadline, |waiter| self.register_reader(waiter));
         }
 
-        writeln!(f, "]")
+        true
     }
-}
 
+    /// Turns the upgradable reader section into an exclusive section, the caller keeps holding the upgrade lock.
+    fn upgrade_reader(&self) {
+        // Writers only wait for the busy flag, so nested read guards would keep accessing the object while we write.
+        assert_eq!(self.depth.get(), 1, "Cannot upgrade while other read guards of this instance exist");
 
-#[cfg(test)]
-#[cfg(not(loom))]
-mod tests {
-    use std::{thread, hint::black_box};
-    use rand::prelude::*;
+        self.shared.id.released(self.index);
+        self.shared.id.check(Access::Write);
 
-    use crate::bf_sharedmutex::BfSharedMutex;
+        // Leave our own reader section, the writer protocol then also waits for our busy flag which is fine.
+        self.depth.set(0);
+        self.control().busy.store(false, Ordering::SeqCst);
+        self.shared.forbid_and_wait(&self.control().counters, None);
+    }
+
+    /// Enters the reader section before any other instance is allowed to continue, the caller must release the
+    /// upgrade lock and construct the read guard afterwards.
+    fn downgrade_writer(&self) {
+        self.shared.id.released(self.index);
+        self.control().busy.store(true, Ordering::SeqCst);
+        lock(&self.shared.other).release(self.shared.policy);
+    }
+
+    /// Leaves the exclusive section, the caller must release the upgrade lock afterwards.
+    fn exit_writer(&self, panicking: bool) {
+        // The object might be left in an inconsistent state when we panicked during the exclusive section.
+        if !panicking && self::panicking() {
+            self.shared.poisoned.store(true, Ordering::SeqCst);
+        }
+
+        // Allow other threads to acquire access to the shared mutex.
+        self.shared.id.released(self.index);
+        lock(&self.shared.other).release(self.shared.policy);
+    }
+
+    /// Registers a reader that waits until the forbidden flag of this instance is cleared.
+    fn register_reader(&self, waiter: Waiter) {
+        let mut waiters = lock(&self.control().waiters);
+        register(&mut waiters, waiter);
+        self.control().waiting.store(true, Ordering::SeqCst);
+    }
+
+    /// Clears the busy flag, and wakes up the writer when it might be waiting for this instance or for the policy.
+    fn clear_busy(&self) {
+        self.control().busy.store(false, Ordering::SeqCst);
+        #[cfg(loom)]
+        core::sync::atomic::fence(Ordering::SeqCst);
+        // Only a reader-preferring writer waits for the busy flags before it has forbidden access.
+        let pending = self.shared.policy == FairnessPolicy::ReaderPreferring && self.shared.pending.load(Ordering::SeqCst);
+        if self.control().forbidden.load(Ordering::SeqCst) || pending {
+            self.shared.wake_writer();
+        }
+    }
+
+    /// Returns true iff a thread panicked while it had exclusive access to the object.
+    pub fn is_poisoned(&self) -> bool {
+        self.shared.poisoned.load(Ordering::SeqCst)
+    }
+
+    /// Clears the poisoned state, for example after the object has been restored to a consistent state.
+    pub fn clear_poison(&self) {
+        self.shared.poisoned.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns the statistics of this instance and of all instances together, only available with the stats feature.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> BfSharedMutexStats {
+        let other = lock(&self.shared.other);
+
+        let mut total = other.retired;
+        for control in other.controls.iter() {
+            total += control.counters.snapshot();
+        }
+
+        BfSharedMutexStats {
+            handle: self.control().counters.snapshot(),
+            total,
+        }
+    }
+
+    /// Returns the guard as a [PoisonError] when the shared mutex is poisoned.
+    fn poison<G>(&self, guard: G) -> LockResult<G> {
+        if self.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Constructs the read guard and enters a (nested) reader section, the caller must have acquired shared access according to the protocol.
+    fn read_guard(&self) -> BfSharedMutexReadGuard<'_, T> {
+        self.depth.set(self.depth.get() + 1);
+        self.control().counters.read();
+        if self.depth.get() == 1 {
+            self.shared.id.held(self.index, Access::Read);
+        }
+
+        #[cfg(loom)]
+        return BfSharedMutexReadGuard {
+            mutex: self,
+            access: self.shared.object.get(),
+        };
+
+        #[cfg(not(loom))]
+        BfSharedMutexReadGuard {
+            mutex: self,
+        }
+    }
+
+    /// Constructs the write guard, the caller must have acquired exclusive access according to the protocol.
+    fn write_guard<'a>(&'a self, upgrade: ExclusiveGuard<'a>) -> BfSharedMutexWriteGuard<'a, T> {
+        self.control().counters.write();
+        self.shared.id.held(self.index, Access::Write);
+
+        #[cfg(loom)]
+        return BfSharedMutexWriteGuard {
+            mutex: self,
+            _upgrade: upgrade,
+            panicking: panicking(),
+            access: self.shared.object.get_mut(),
+        };
+
+        #[cfg(not(loom))]
+        BfSharedMutexWriteGuard {
+            mutex: self,
+            _upgrade: upgrade,
+            panicking: panicking(),
+        }
+    }
+
+    /// Obtain mutable access to the object without locking, is safe because we have mutable access.
+    #[cfg(not(loom))]
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe {
+            &mut *self.shared.object.get()
+        }
+    }
+
+    /// Returns the number of instances of this shared mutex, including this one.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.shared)
+    }
+
+    /// Takes the object out of the shared mutex when this is the last instance, otherwise returns the instance.
+    pub fn into_inner(self) -> Result<T, Self> {
+        // Other instances can only be created by cloning one, so no instance can appear after this check.
+        if self.handle_count() != 1 {
+            return Err(self);
+        }
+
+        // The slot of this instance does not have to be freed, since the slab is dropped with the shared data.
+        let this = ManuallyDrop::new(self);
+        let shared = unsafe { ptr::read(&this.shared) };
+
+        match Arc::try_unwrap(shared) {
+            Ok(shared) => Ok(CachePadded::into_inner(shared).object.into_inner()),
+            Err(_) => unreachable!("This is the last instance of the shared mutex"),
+        }
+    }
+}
+
+impl<T: Debug> Debug for BfSharedMutex<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        
+        f.debug_map().entry(&"busy", &self.control().busy.load(Ordering::SeqCst))
+        .entry(&"forbidden", &self.control().forbidden.load(Ordering::SeqCst))
+        .entry(&"index", &self.index)
+        .entry(&"depth", &self.depth.get())
+        .entry(&"len(other)", &lock(&self.shared.other).controls.live())
+        .finish()?;
+
+        writeln!(f)?;
+        writeln!(f, "other values: [")?;
+        for control in lock(&self.shared.other).controls.iter() {
+            f.debug_map().entry(&"busy", &control.busy.load(Ordering::SeqCst))
+            .entry(&"forbidden", &control.forbidden.load(Ordering::SeqCst))
+            .finish()?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "]")
+    }
+}
+
+
+#[cfg(test)]
+#[cfg(not(loom))]
+#[cfg(feature = "std")]
+mod tests {
+    use std::{sync::Arc, thread, hint::black_box, time::{Duration, Instant}};
+    use rand::prelude::*;
+
+    use std::sync::atomic::Ordering;
+
+    use crate::{BfRwLock, BfSharedMutex, BfSharedMutexError, BfSharedMutexMappedReadGuard, BfSharedMutexOwnedReadGuard, BfSharedMutexReadGuard, BfSharedMutexWriteGuard, FairnessPolicy, TryLockError, waiter::lock};
+
+    /// Polls the condition until it holds, returns false when it did not hold within a few seconds.
+    fn eventually(mut condition: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            if Instant::now() > deadline {
+                return false;
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        true
+    }
 
     // These are just simple tests.
     #[test]
@@ -343,9 +1147,9 @@ mod tests {
                     if rng.gen_bool(0.95) {
                         // Read a random index.
                         let read = shared_vector.read().unwrap();
-                        if read.len() > 0 {
+                        if !read.is_empty() {
                             let index = rng.gen_range(0..read.len());
-                            black_box(assert_eq!(read[index], 5));
+                            assert_eq!(black_box(read[index]), 5);
                         }
                     } else {
                         // Add a new vector element.
@@ -361,6 +1165,982 @@ mod tests {
             thread.join().unwrap();
         }
     }
+
+    #[test]
+    fn test_try_lock() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        {
+            let _read = shared_number.read().unwrap();
+
+            // Readers can coexist, but a writer must wait for the reader section to end.
+            assert!(other.try_read().is_ok());
+            assert!(matches!(other.try_write(), Err(TryLockError::WouldBlock)));
+        }
+
+        {
+            let mut write = other.try_write().unwrap();
+            *write += 1;
+
+            assert!(matches!(shared_number.try_read(), Err(TryLockError::WouldBlock)));
+            assert!(matches!(shared_number.try_write(), Err(TryLockError::WouldBlock)));
+        }
+
+        // The failed attempts should not have left any flags behind.
+        assert_eq!(*shared_number.try_read().unwrap(), 6);
+        assert_eq!(*shared_number.write().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+        let third = shared_number.clone();
+
+        {
+            let _read = shared_number.read().unwrap();
+
+            assert!(matches!(other.write_for(Duration::from_millis(10)), Err(TryLockError::TimedOut)));
+
+            // The writer must have rolled back its forbidden flags.
+            assert!(third.try_read().is_ok());
+        }
+
+        {
+            let _write = other.write_for(Duration::from_millis(10)).unwrap();
+
+            assert!(matches!(third.read_for(Duration::from_millis(10)), Err(TryLockError::TimedOut)));
+        }
+
+        assert_eq!(*third.read_until(Instant::now() + Duration::from_millis(10)).unwrap(), 5);
+        assert_eq!(*shared_number.write_until(Instant::now() + Duration::from_millis(10)).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_upgradable() {
+        let shared_number = BfSharedMutex::new(0);
+        let other = shared_number.clone();
+
+        {
+            let upgradable = shared_number.upgradable_read().unwrap();
+
+            // Plain readers can coexist with the upgradable reader, but writers cannot.
+            assert_eq!(*other.try_read().unwrap(), 0);
+            assert!(matches!(other.try_write(), Err(TryLockError::WouldBlock)));
+
+            *upgradable.upgrade().unwrap() += 1;
+        }
+
+        let mut threads = vec![];
+        let num_threads = 20;
+        let num_iterations = 500;
+
+        for _ in 0..num_threads {
+            let shared_number = shared_number.clone();
+            threads.push(thread::spawn(move || {
+                for _ in 0..num_iterations {
+                    // No writer can change the value between reading and upgrading.
+                    let upgradable = shared_number.upgradable_read().unwrap();
+                    let value = *upgradable;
+
+                    *upgradable.upgrade().unwrap() = value + 1;
+                }
+            }));
+        }
+
+        // Check whether threads have completed succesfully.
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(*shared_number.read().unwrap(), num_threads * num_iterations + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "inside a reader section")]
+    fn test_upgradable_nested() {
+        let shared_number = BfSharedMutex::new(5);
+        let _read = shared_number.read().unwrap();
+
+        // Leaving the upgradable section would clear the busy flag of the outer section.
+        let _upgradable = shared_number.upgradable_read();
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        let mut write = shared_number.write().unwrap();
+        *write += 1;
+
+        let read = write.downgrade();
+        assert_eq!(*read, 6);
+
+        // Other readers can continue, but writers have to wait for the downgraded guard.
+        assert_eq!(*other.try_read().unwrap(), 6);
+        assert!(matches!(other.try_write(), Err(TryLockError::WouldBlock)));
+
+        drop(read);
+        assert!(other.try_write().is_ok());
+    }
+
+    #[test]
+    fn test_error_propagation() -> Result<(), BfSharedMutexError> {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        // The error does not borrow the guard, so it can be stored after the handles are gone.
+        let error = {
+            let _write = shared_number.write()?;
+            other.try_read().err().map(BfSharedMutexError::from)
+        };
+
+        drop(other);
+        assert_eq!(error, Some(BfSharedMutexError::WouldBlock));
+
+        assert_eq!(*shared_number.read()?, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_poison() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        let result = thread::spawn(move || {
+            let mut write = other.write().unwrap();
+            *write += 1;
+            panic!("Panic while holding the write guard");
+        }).join();
+        assert!(result.is_err());
+
+        // The panic should not cascade into other handles.
+        assert!(shared_number.is_poisoned());
+        let other = shared_number.clone();
+        assert!(matches!(other.try_write(), Err(TryLockError::Poisoned(_))));
+        drop(other);
+
+        let error = shared_number.read().err().unwrap();
+        assert_eq!(*error.into_inner(), 6);
+
+        shared_number.clear_poison();
+        assert!(!shared_number.is_poisoned());
+        assert_eq!(*shared_number.write().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_nested_read() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        let read = shared_number.read().unwrap();
+        let writer = thread::spawn(move || {
+            *other.write().unwrap() += 1;
+        });
+
+        // Give the writer time to forbid access, the nested section must still be able to continue.
+        thread::sleep(Duration::from_millis(10));
+        let nested = shared_number.read().unwrap();
+        assert_eq!(*nested, 5);
+        assert_eq!(*shared_number.try_read().unwrap(), 5);
+
+        // The writer can only continue after the outermost section ends.
+        drop(read);
+        thread::sleep(Duration::from_millis(10));
+        assert!(!writer.is_finished());
+
+        drop(nested);
+        writer.join().unwrap();
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_try_write_while_registry_held() {
+        let shared_number = BfSharedMutex::new(5);
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+
+        // Another instance holding the registry briefly is no contention for the lock itself.
+        let holder = {
+            let other = shared_number.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let _registry = lock(&other.shared.other);
+                barrier.wait();
+                thread::sleep(Duration::from_millis(50));
+            })
+        };
+
+        barrier.wait();
+        *shared_number.try_write().unwrap() = 6;
+        holder.join().unwrap();
+
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_registry_reuse() {
+        let shared_number = BfSharedMutex::new(0);
+        let mut handles: Vec<_> = (0..10).map(|_| shared_number.clone()).collect();
+
+        // Short lived handles should not grow the table.
+        for _ in 0..1000 {
+            drop(shared_number.clone());
+        }
+        assert_eq!(lock(&shared_number.shared.other).controls.iter().count(), 11);
+
+        // Removing handles frees their slots, which are then reused by new handles.
+        handles.drain(2..5);
+        assert_eq!(lock(&shared_number.shared.other).controls.iter().count(), 8);
+        handles.extend((0..3).map(|_| shared_number.clone()));
+        assert_eq!(lock(&shared_number.shared.other).controls.iter().count(), 11);
+
+        let read = handles.last().unwrap().read().unwrap();
+        assert!(matches!(shared_number.try_write(), Err(TryLockError::WouldBlock)));
+        drop(read);
+
+        *shared_number.write().unwrap() += 1;
+        for handle in &handles {
+            assert_eq!(*handle.read().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let shared_vector = BfSharedMutex::new(vec![1]);
+        let other = shared_vector.clone();
+        assert_eq!(shared_vector.handle_count(), 2);
+
+        thread::spawn(move || {
+            other.write().unwrap().push(2);
+        }).join().unwrap();
+
+        // The object can only be taken once the other instances have been dropped.
+        let other = shared_vector.clone().into_inner().unwrap_err();
+        drop(other);
+
+        assert_eq!(shared_vector.handle_count(), 1);
+        assert_eq!(shared_vector.into_inner().ok(), Some(vec![1, 2]));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let shared_vector = BfSharedMutex::new(vec![1, 2, 3]);
+        let json = serde_json::to_string(&shared_vector).unwrap();
+        assert_eq!(json, "[1,2,3]");
+
+        let shared_vector: BfSharedMutex<Vec<u32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(*shared_vector.read().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_write_combined() {
+        let shared_vector = BfSharedMutex::new(vec![]);
+
+        thread::scope(|s| {
+            for t in 0..8 {
+                let shared_vector = shared_vector.clone();
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        let len = shared_vector.write_combined(|vector| {
+                            vector.push(t * 1000 + i);
+                            vector.len()
+                        }).unwrap();
+                        assert!(len > i);
+
+                        if i % 10 == 0 {
+                            assert!(!shared_vector.read().unwrap().is_empty());
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut vector = shared_vector.read().unwrap().clone();
+        vector.sort_unstable();
+        assert_eq!(vector, (0..8000).collect::<Vec<_>>());
+
+        // A panic is propagated to the publishing thread, and poisons the shared mutex.
+        let other = shared_vector.clone();
+        thread::spawn(move || {
+            other.write_combined(|_| panic!("This panic is intended")).unwrap();
+        }).join().unwrap_err();
+        assert!(shared_vector.write_combined(|vector| vector.len()).is_err());
+    }
+
+    #[test]
+    fn test_write_combined_not_queued() {
+        let shared_number = BfSharedMutex::new(5);
+        let mut write = shared_number.write().unwrap();
+
+        thread::scope(|s| {
+            let publisher = shared_number.clone();
+            s.spawn(move || assert_eq!(publisher.write_combined(|number| *number + 1).unwrap(), 7));
+
+            let published = || lock(&shared_number.shared.other).controls.iter()
+                .any(|control| !control.combined.load(Ordering::SeqCst).is_null());
+            while !published() {
+                thread::yield_now();
+            }
+
+            // The publisher waits for the upgrade lock, or for its request to be applied, without queueing as a writer.
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(lock(&shared_number.shared.other).queued, 0);
+
+            *write = 6;
+            drop(write);
+        });
+
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_max_handles() {
+        let shared_number = BfSharedMutex::with_max_handles(5, 3);
+        let mut handles = vec![shared_number.clone(), shared_number.try_clone().unwrap()];
+
+        assert_eq!(shared_number.try_clone().err(), Some(BfSharedMutexError::RegistryFull));
+
+        // Dropping a handle frees its slot for the next clone.
+        handles.pop();
+        handles.push(shared_number.try_clone().unwrap());
+
+        *handles[1].write().unwrap() = 6;
+        assert_eq!(*handles[0].read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_max_handles_parking() {
+        let shared_number = BfSharedMutex::with_max_handles(5, 2);
+        let writer = shared_number.clone();
+
+        // The reader parks on its own control block, which already has room for its waiter.
+        let (buffer, capacity) = {
+            let waiters = lock(&shared_number.control().waiters);
+            (waiters.as_ptr(), waiters.capacity())
+        };
+        assert!(capacity > 0);
+
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let thread = {
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let write = writer.write().unwrap();
+                barrier.wait();
+
+                let parked = eventually(|| writer.shared.blocked.load(Ordering::SeqCst) == 1);
+                drop(write);
+                parked
+            })
+        };
+
+        barrier.wait();
+        assert_eq!(*shared_number.read().unwrap(), 5);
+        assert!(thread.join().unwrap(), "The reader did not park itself");
+
+        let waiters = lock(&shared_number.control().waiters);
+        assert_eq!((waiters.as_ptr(), waiters.capacity()), (buffer, capacity));
+    }
+
+    #[test]
+    fn test_parked_writer() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        let read = shared_number.read().unwrap();
+        let writer = thread::spawn(move || {
+            *other.write().unwrap() += 1;
+        });
+
+        // After spinning for a while the writer should park itself until the reader leaves.
+        let parked = eventually(|| lock(&shared_number.shared.writer).is_some());
+
+        drop(read);
+        writer.join().unwrap();
+        assert!(parked, "The writer did not park itself");
+
+        assert!(lock(&shared_number.shared.writer).is_none());
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_parked_readers() {
+        let shared_number = BfSharedMutex::new(5);
+        let num_threads = 8;
+
+        let write = shared_number.write().unwrap();
+
+        // Instances registered during the exclusive section must also wait for the writer.
+        let mut threads = vec![];
+        for _ in 0..num_threads {
+            let shared_number = shared_number.clone();
+            threads.push(thread::spawn(move || {
+                assert_eq!(*shared_number.read().unwrap(), 5);
+            }));
+        }
+
+        // All readers should park themselves on their own instance until the writer is done.
+        let parked = eventually(|| {
+            let other = lock(&shared_number.shared.other);
+            other.controls.iter().filter(|control| control.waiting.load(Ordering::SeqCst)).count() == num_threads
+        });
+
+        // Release the writer before asserting, a failed assertion would poison the readers as well.
+        drop(write);
+        assert!(parked, "Not all readers parked themselves");
+
+        // Check whether threads have completed succesfully.
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mapped() {
+        let shared_vector = BfSharedMutex::new(vec![1, 2, 3]);
+        let other = shared_vector.clone();
+
+        {
+            let mut element = BfSharedMutexWriteGuard::map(shared_vector.write().unwrap(), |vector| &mut vector[1]);
+            *element = 5;
+
+            // The mapped guard keeps the exclusive section alive.
+            assert!(matches!(other.try_read(), Err(TryLockError::WouldBlock)));
+        }
+
+        let read = shared_vector.read().unwrap();
+        let Err(read) = BfSharedMutexReadGuard::filter_map(read, |vector| vector.get(3)) else {
+            panic!("The vector has no fourth element");
+        };
+        let element = BfSharedMutexReadGuard::map(read, |vector| &vector[1..]);
+        let element = BfSharedMutexMappedReadGuard::try_map(element, |slice| slice.first().ok_or("empty")).ok().unwrap();
+        assert_eq!(*element, 5);
+
+        // Writers wait until the mapped reader section has ended.
+        assert!(matches!(other.try_write(), Err(TryLockError::WouldBlock)));
+        drop(element);
+        assert!(other.try_write().is_ok());
+    }
+
+    #[test]
+    fn test_owned() {
+        struct Session {
+            lease: BfSharedMutexOwnedReadGuard<usize>,
+        }
+
+        let shared_number = BfSharedMutex::new(5);
+        let session = Session { lease: shared_number.clone().read_owned().unwrap() };
+
+        assert_eq!(*session.lease, 5);
+        assert!(matches!(shared_number.try_write(), Err(TryLockError::WouldBlock)));
+        drop(session);
+
+        // The owned write guard can be moved to another thread.
+        let mut write = shared_number.clone().write_owned().unwrap();
+        thread::spawn(move || {
+            *write = 6;
+        }).join().unwrap();
+
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_writer_preferring() {
+        let shared_number = BfSharedMutex::with_policy(5, FairnessPolicy::WriterPreferring);
+        let reader = shared_number.clone();
+        let write = shared_number.write().unwrap();
+
+        let writer = {
+            let shared_number = shared_number.clone();
+            thread::spawn(move || {
+                let mut write = shared_number.write().unwrap();
+                thread::sleep(Duration::from_millis(50));
+                *write = 6;
+            })
+        };
+
+        while lock(&shared_number.shared.other).queued == 0 {
+            thread::yield_now();
+        }
+
+        // The queued writer is handed the exclusive section, so readers cannot enter in between.
+        drop(write);
+        assert!(matches!(reader.try_read(), Err(TryLockError::WouldBlock)));
+        assert_eq!(*reader.read().unwrap(), 6);
+
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_phase_fair() {
+        let shared_number = BfSharedMutex::with_policy(5, FairnessPolicy::PhaseFair);
+        let mut write = shared_number.write().unwrap();
+
+        let reader = {
+            let shared_number = shared_number.clone();
+            thread::spawn(move || {
+                assert_eq!(*shared_number.read().unwrap(), 6);
+            })
+        };
+
+        while shared_number.shared.blocked.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+
+        let writer = {
+            let shared_number = shared_number.clone();
+            thread::spawn(move || {
+                *shared_number.write().unwrap() = 7;
+            })
+        };
+
+        while lock(&shared_number.shared.other).queued == 0 {
+            thread::yield_now();
+        }
+
+        // The blocked reader enters before the queued writer, so it must observe this write.
+        *write = 6;
+        drop(write);
+
+        reader.join().unwrap();
+        writer.join().unwrap();
+        assert_eq!(*shared_number.read().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let local = tokio::task::LocalSet::new();
+        let shared_number = BfSharedMutex::new(5);
+
+        local.block_on(&runtime, async {
+            let writer = {
+                let shared_number = shared_number.clone();
+                tokio::task::spawn_local(async move {
+                    let mut write = shared_number.write_async().await.unwrap();
+
+                    // The reader task runs in between and must wait for us.
+                    tokio::task::yield_now().await;
+                    *write = 6;
+                })
+            };
+
+            let reader = {
+                let shared_number = shared_number.clone();
+                tokio::task::spawn_local(async move {
+                    assert_eq!(*shared_number.read_async().await.unwrap(), 6);
+                })
+            };
+
+            writer.await.unwrap();
+            reader.await.unwrap();
+
+            // A reader on another thread wakes up the waiting writer task.
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            let thread = {
+                let shared_number = shared_number.clone();
+                thread::spawn(move || {
+                    let read = shared_number.read().unwrap();
+                    sender.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                    assert_eq!(*read, 6);
+                })
+            };
+
+            receiver.await.unwrap();
+            *shared_number.write_async().await.unwrap() = 7;
+            thread.join().unwrap();
+        });
+
+        assert_eq!(*shared_number.read().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_async_owned() {
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(4).build().unwrap();
+        let shared_number = BfSharedMutex::new(0);
+
+        runtime.block_on(async {
+            let tasks: Vec<_> = (0..8).map(|_| {
+                let shared_number = shared_number.clone();
+                tokio::spawn(async move {
+                    for _ in 0..100 {
+                        let before = *shared_number.clone().read_owned_async().await.unwrap();
+
+                        // The guard is held across an await point, so the task can resume on another worker.
+                        let mut write = shared_number.clone().write_owned_async().await.unwrap();
+                        tokio::task::yield_now().await;
+                        assert!(*write >= before);
+                        *write += 1;
+                    }
+                })
+            }).collect();
+
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+
+        assert_eq!(*shared_number.read().unwrap(), 800);
+    }
+
+    #[test]
+    fn test_async_repeated_poll() {
+        use std::{future::Future, pin::pin, task::{Context, Wake, Waker}};
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let shared_number = BfSharedMutex::new(5);
+        let reader = shared_number.clone();
+        let write = shared_number.write().unwrap();
+
+        // A future that is polled again while it waits, for example in a select loop, keeps a single waker.
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut read = pin!(reader.read_async());
+        for _ in 0..100 {
+            assert!(read.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(lock(&reader.control().waiters).len(), 1);
+
+        drop(write);
+        assert!(read.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn test_async_policy_wake() {
+        use std::{future::Future, pin::pin, sync::atomic::AtomicUsize, task::{Context, Poll, Wake, Waker}};
+
+        struct CountingWaker(AtomicUsize);
+
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let shared_number = BfSharedMutex::with_policy(5, FairnessPolicy::ReaderPreferring);
+        let reader = shared_number.clone();
+        let read = reader.read().unwrap();
+
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut write = pin!(shared_number.write_async());
+
+        // The writer waits for the policy without waking itself up, the reader wakes it when it leaves.
+        assert!(write.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        drop(read);
+        assert!(counter.0.load(Ordering::SeqCst) > 0);
+
+        match write.as_mut().poll(&mut cx) {
+            Poll::Ready(guard) => *guard.unwrap() = 6,
+            Poll::Pending => panic!("The writer must be ready after the reader left"),
+        }
+
+        assert_eq!(*reader.read().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_rwlock() {
+        let rwlock = BfRwLock::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        let value = *rwlock.read().unwrap();
+                        assert!(value <= 4000);
+                        *rwlock.write().unwrap() += 1;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*rwlock.read().unwrap(), 4000);
+
+        // The object is dropped with the lock, even though this thread still caches its instance.
+        let object = Arc::new(());
+        let rwlock = BfRwLock::new(object.clone());
+        assert_eq!(Arc::strong_count(&rwlock.read().unwrap()), 2);
+        drop(rwlock);
+        assert_eq!(Arc::strong_count(&object), 1);
+    }
+
+    #[test]
+    fn test_rwlock_cache() {
+        use super::rwlock::cached_handles;
+
+        // A fresh thread, so that the cache only contains the locks of this test.
+        thread::spawn(|| {
+            let first = BfRwLock::new(1);
+            let second = BfRwLock::new(2);
+            for _ in 0..3 {
+                assert_eq!(*first.read().unwrap() + *second.read().unwrap(), 3);
+            }
+            assert_eq!(cached_handles(), 2);
+
+            // The instance of a dropped lock is pruned when another cached lock is accessed.
+            drop(second);
+            assert_eq!(*first.read().unwrap(), 1);
+            assert_eq!(cached_handles(), 1);
+        }).join().unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn test_stats() {
+        let shared_number = BfSharedMutex::new(5);
+        let other = shared_number.clone();
+
+        {
+            let _read = shared_number.read().unwrap();
+            let _nested = shared_number.read().unwrap();
+            assert!(other.try_write().is_err());
+        }
+
+        *other.write().unwrap() += 1;
+
+        // A reader that is forbidden by a writer takes the slow path.
+        let write = other.write().unwrap();
+        thread::scope(|s| {
+            let reader = shared_number.clone();
+            let handle = s.spawn(move || {
+                assert_eq!(*reader.read().unwrap(), 6);
+                reader.stats().handle
+            });
+
+            // The reader starts measuring before it counts itself as blocked, so it waits at least as long as we sleep.
+            let blocked = eventually(|| other.shared.blocked.load(Ordering::SeqCst) == 1);
+            thread::sleep(Duration::from_millis(10));
+            drop(write);
+
+            let stats = handle.join().unwrap();
+            assert!(blocked, "The reader was not blocked by the writer");
+            assert_eq!((stats.reads, stats.forbidden_reads), (1, 1));
+            assert!(stats.wait_time >= Duration::from_millis(10));
+        });
+
+        let stats = shared_number.stats();
+        assert_eq!((stats.handle.reads, stats.handle.writes), (2, 0));
+        assert_eq!((stats.total.reads, stats.total.writes, stats.total.forbidden_reads), (3, 2, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "stats")]
+    fn test_stats_while_writer_waits() {
+        let shared_number = BfSharedMutex::new(5);
+        let read = shared_number.read().unwrap();
+
+        thread::scope(|s| {
+            let writer = shared_number.clone();
+            s.spawn(move || *writer.write().unwrap() = 6);
+
+            // Once our instance is forbidden the writer waits for this reader section to end.
+            while !shared_number.control().forbidden.load(Ordering::SeqCst) {
+                thread::yield_now();
+            }
+
+            assert_eq!(shared_number.stats().total.writes, 0);
+            drop(read);
+        });
+
+        assert_eq!(*shared_number.read().unwrap(), 6);
+    }
+
+    #[test]
+    #[cfg(feature = "deadlock-detection")]
+    fn test_deadlock_detection() {
+        let first = BfSharedMutex::new(0);
+        let second = BfSharedMutex::new(0);
+
+        // Establish the order in which both shared mutexes are acquired.
+        {
+            let _read = first.read().unwrap();
+            *second.write().unwrap() += 1;
+        }
+
+        let reversed = {
+            let first = first.clone();
+            let second = second.clone();
+            thread::spawn(move || {
+                let _write = second.write().unwrap();
+                let _read = first.read().unwrap();
+            })
+        };
+
+        // The backtraces are captured regardless of RUST_BACKTRACE.
+        let error = reversed.join().unwrap_err();
+        let message = error.downcast_ref::<String>().unwrap();
+        assert!(message.starts_with("Potential deadlock"));
+        assert!(!message.contains("disabled backtrace"));
+
+        // Writing through another instance while this thread is reading would wait for itself.
+        let other = first.clone();
+        let misuse = thread::spawn(move || {
+            let _read = first.read().unwrap();
+            let _write = other.write().unwrap();
+        });
+
+        let error = misuse.join().unwrap_err();
+        assert!(error.downcast_ref::<String>().unwrap().starts_with("Deadlock"));
+    }
+
+    #[test]
+    #[cfg(feature = "deadlock-detection")]
+    fn test_deadlock_detection_readers() {
+        let first = BfSharedMutex::new(0);
+        let second = BfSharedMutex::new(0);
+
+        // Reading both shared mutexes establishes their order, even though the readers do not exclude each other.
+        {
+            let _first = first.read().unwrap();
+            let _second = second.read().unwrap();
+        }
+
+        // A writer of the second one can wait for the reader, while the reader waits for the writer of the first one.
+        let reversed = thread::spawn(move || {
+            let _second = second.write().unwrap();
+            let _first = first.write().unwrap();
+        });
+
+        let error = reversed.join().unwrap_err();
+        assert!(error.downcast_ref::<String>().unwrap().starts_with("Potential deadlock"));
+    }
+
+    #[test]
+    #[cfg(feature = "lock_api")]
+    fn test_lock_api() {
+        use std::sync::Barrier;
+
+        use lock_api::{RwLockUpgradableReadGuard, RwLockWriteGuard};
+
+        use crate::BfLockApiRwLock;
+
+        let rwlock = BfLockApiRwLock::new(0);
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1000 {
+                        *rwlock.write() += 1;
+                        assert!(*rwlock.read() > 0);
+                    }
+                });
+            }
+        });
+
+        let read = rwlock.upgradable_read();
+        assert_eq!(*read, 4000);
+
+        // The upgrade has to wait for the readers of other threads, but the upgradable reader excludes writers.
+        let barrier = Barrier::new(2);
+        let read = thread::scope(|s| {
+            s.spawn(|| {
+                let _other = rwlock.read();
+                assert!(rwlock.try_write().is_none());
+                barrier.wait();
+                barrier.wait();
+            });
+
+            barrier.wait();
+            let read = RwLockUpgradableReadGuard::try_upgrade(read).unwrap_err();
+            barrier.wait();
+            read
+        });
+
+        let mut write = RwLockUpgradableReadGuard::try_upgrade(read).unwrap();
+        *write += 1;
+        assert!(rwlock.is_locked());
+
+        let read = RwLockWriteGuard::downgrade(write);
+        assert_eq!(*read, 4001);
+        drop(read);
+
+        assert!(!rwlock.is_locked());
+    }
+
+    #[test]
+    #[cfg(feature = "lock_api")]
+    fn test_lock_api_is_locked_while_writer_waits() {
+        use crate::BfLockApiRwLock;
+
+        let rwlock = BfLockApiRwLock::new(0);
+        let read = rwlock.read();
+
+        thread::scope(|s| {
+            s.spawn(|| *rwlock.write() = 1);
+
+            // Give the writer time to forbid our instance, it then waits for this reader section to end.
+            thread::sleep(Duration::from_millis(50));
+            assert!(rwlock.is_locked());
+            drop(read);
+        });
+
+        assert_eq!(*rwlock.read(), 1);
+        assert!(!rwlock.is_locked());
+    }
+
+    #[test]
+    #[cfg(feature = "lock_api")]
+    fn test_lock_api_try_upgrade_while_registry_held() {
+        use std::sync::Barrier;
+
+        use lock_api::RwLockUpgradableReadGuard;
+
+        use crate::BfLockApiRwLock;
+
+        let rwlock = BfLockApiRwLock::new(0);
+        let read = rwlock.upgradable_read();
+
+        // Another thread holding the registry briefly, for example while it clones or drops its instance, is no
+        // contention for the lock itself.
+        let barrier = Barrier::new(2);
+        let mut write = thread::scope(|s| {
+            s.spawn(|| {
+                let handle = unsafe { rwlock.raw() }.handle();
+                let _registry = lock(&handle.shared.other);
+                barrier.wait();
+                thread::sleep(Duration::from_millis(50));
+            });
+
+            barrier.wait();
+            RwLockUpgradableReadGuard::try_upgrade(read).unwrap()
+        });
+
+        *write += 1;
+        drop(write);
+        assert_eq!(*rwlock.read(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_worker_handles() {
+        use rayon::prelude::*;
+
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let shared_number = BfSharedMutex::new(0);
+        let handles = shared_number.worker_handles_in(&pool);
+        assert_eq!(handles.len(), 4);
+
+        pool.install(|| {
+            (0..1000).into_par_iter().for_each(|_| {
+                *handles.get().unwrap().write().unwrap() += 1;
+            });
+        });
+
+        // Only the workers of the pool can obtain an instance.
+        assert!(handles.get().is_none());
+        assert_eq!(*shared_number.read().unwrap(), 1000);
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,127 @@
+use std::{
+    cell::UnsafeCell, panic::{self, AssertUnwindSafe}, ptr::{self, NonNull}, sync::atomic::{AtomicBool, Ordering}, thread
+};
+
+use crossbeam::utils::Backoff;
+
+use crate::{waiter::{lock, snooze}, LockResult};
+
+use super::{deadlock::Access, BfSharedMutex};
+
+/// A write that an instance has published in its control bits, it is applied by the thread that combines the writes.
+pub(super) struct Request {
+    /// Applies the closure to the object, and returns true iff it panicked.
+    apply: unsafe fn(NonNull<Request>, *mut ()) -> bool,
+
+    /// Set once the closure has been applied, after which the request is no longer accessed by the combining thread.
+    done: AtomicBool,
+
+    /// The publishing thread, which is woken up once the request is done.
+    thread: thread::Thread,
+}
+
+/// The request together with the closure and its result, which lives on the stack of the publishing thread.
+#[repr(C)]
+struct Combined<F, R> {
+    request: Request,
+    closure: UnsafeCell<Option<F>>,
+    result: UnsafeCell<Option<thread::Result<R>>>,
+}
+
+impl<T> BfSharedMutex<T> {
+
+    /// Applies the closure with write access like [BfSharedMutex::write], but combines it with the closures of other
+    /// instances. The closure is published, and whichever thread acquires the upgrade lock applies all published
+    /// closures within a single exclusive section, so that the readers are only forbidden and drained once.
+    ///
+    /// The closure can run on another thread, a panic is propagated to the thread that published it.
+    pub fn write_combined<F, R>(&self, f: F) -> LockResult<R>
+    where
+        F: FnOnce(&mut T) -> R + Send,
+        R: Send,
+    {
+        self.shared.id.check(Access::Write);
+
+        debug_assert!(!self.control().busy.load(Ordering::SeqCst),
+            "Can only exclusive lock outside of a shared lock, no upgrading!");
+
+        let combined = Combined {
+            request: Request {
+                apply: apply::<T, F, R>,
+                done: AtomicBool::new(false),
+                thread: thread::current(),
+            },
+            closure: UnsafeCell::new(Some(f)),
+            result: UnsafeCell::new(None),
+        };
+
+        // The request stays alive until it is done, since we only return afterwards.
+        self.control().combined.store(NonNull::from(&combined).cast::<Request>().as_ptr(), Ordering::Release);
+
+        // Wait until our closure has been applied or we can combine the writes ourselves. We do not queue as a writer,
+        // since the combining thread most likely applies our closure, and then wakes us up.
+        let backoff = Backoff::new();
+        let mut registered = false;
+        let upgrade = loop {
+            if combined.request.done.load(Ordering::Acquire) {
+                break None;
+            }
+
+            if let Some(upgrade) = self.shared.upgrade.try_lock() {
+                break Some(upgrade);
+            }
+
+            // Register before checking again, so that either the combining thread or the release wakes us up.
+            snooze(&backoff, &mut registered, None, |waiter| self.shared.upgrade.register(waiter));
+        };
+
+        if let Some(upgrade) = upgrade {
+            if combined.request.done.load(Ordering::Acquire) {
+                // The previous holder of the upgrade lock has applied our closure.
+                drop(upgrade);
+            } else {
+                self.shared.forbid_and_wait(&self.control().counters, None);
+
+                let mut guard = self.write_guard(upgrade);
+                self.apply_combined(&mut guard);
+            }
+        }
+
+        match combined.result.into_inner() {
+            Some(Ok(result)) => self.poison(result),
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => unreachable!("The request was applied"),
+        }
+    }
+
+    /// Applies the published closures of all instances, the caller must have exclusive access to the object.
+    fn apply_combined(&self, object: &mut T) {
+        // Take the requests out of the registry first, since the closures could clone this instance.
+        let requests: Vec<_> = lock(&self.shared.other).controls.iter()
+            .filter_map(|control| NonNull::new(control.combined.swap(ptr::null_mut(), Ordering::AcqRel)))
+            .collect();
+
+        for request in requests {
+            // The object might be left in an inconsistent state by a panicking closure.
+            if unsafe { (request.as_ref().apply)(request, object as *mut T as *mut ()) } {
+                self.shared.poisoned.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Applies the closure of a [Combined] request to the object, the closure is caught when it panics.
+unsafe fn apply<T, F: FnOnce(&mut T) -> R, R>(request: NonNull<Request>, object: *mut ()) -> bool {
+    let combined = request.cast::<Combined<F, R>>().as_ref();
+
+    let closure = (*combined.closure.get()).take().expect("The request is only applied once");
+    let result = panic::catch_unwind(AssertUnwindSafe(|| closure(&mut *object.cast::<T>())));
+    let panicked = result.is_err();
+    *combined.result.get() = Some(result);
+
+    // The publishing thread can return as soon as it observes this, so wake it up without accessing the request.
+    let thread = combined.request.thread.clone();
+    combined.request.done.store(true, Ordering::Release);
+    thread.unpark();
+    panicked
+}
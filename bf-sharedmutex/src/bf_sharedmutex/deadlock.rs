@@ -0,0 +1,180 @@
+#[cfg(feature = "deadlock-detection")]
+use std::{
+    backtrace::Backtrace, collections::{HashMap, HashSet}, sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex}, thread::{self, ThreadId}
+};
+
+#[cfg(feature = "deadlock-detection")]
+use crate::waiter::lock;
+
+/// The kind of access that an instance acquires.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Access {
+    Read,
+    Write,
+}
+
+/// Identifies a shared mutex for the deadlock detector, is empty and free when the deadlock-detection feature is
+/// disabled. The detector records which instances hold access on which threads, and the order in which the threads
+/// acquire different shared mutexes. It panics when an acquisition could deadlock, printing both backtraces.
+#[cfg_attr(not(feature = "deadlock-detection"), derive(Default))]
+pub(super) struct LockId {
+    #[cfg(feature = "deadlock-detection")]
+    id: usize,
+}
+
+/// The acquisition of a shared mutex by one of its instances.
+#[cfg(feature = "deadlock-detection")]
+struct Held {
+    thread: ThreadId,
+    access: Access,
+    backtrace: Arc<Backtrace>,
+}
+
+/// Records that a thread acquired a shared mutex while it held another one.
+#[cfg(feature = "deadlock-detection")]
+struct Edge {
+    held: Arc<Backtrace>,
+    acquired: Arc<Backtrace>,
+}
+
+#[cfg(feature = "deadlock-detection")]
+#[derive(Default)]
+struct Detector {
+    /// The acquisitions indexed by the shared mutex and the slot of the instance.
+    held: HashMap<(usize, usize), Held>,
+
+    /// The order in which shared mutexes have been acquired, from the held one to the acquired one.
+    order: HashMap<(usize, usize), Edge>,
+}
+
+#[cfg(feature = "deadlock-detection")]
+static DETECTOR: Mutex<Option<Detector>> = Mutex::new(None);
+
+#[cfg(feature = "deadlock-detection")]
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "deadlock-detection")]
+impl Default for LockId {
+    fn default() -> Self {
+        LockId {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+}
+
+impl LockId {
+
+    /// Checks whether the current thread can block on acquiring the given access, panics when it could deadlock.
+    #[inline]
+    pub(super) fn check(&self, _access: Access) {
+        #[cfg(feature = "deadlock-detection")]
+        self.check_slow(_access);
+    }
+
+    /// Records that the instance with the given slot acquired access on the current thread.
+    #[inline]
+    pub(super) fn held(&self, _index: usize, _access: Access) {
+        #[cfg(feature = "deadlock-detection")]
+        {
+            let backtrace = Arc::new(Backtrace::force_capture());
+            let mut detector = lock(&DETECTOR);
+            detector.get_or_insert_with(Detector::default).held.insert((self.id, _index), Held {
+                thread: thread::current().id(),
+                access: _access,
+                backtrace,
+            });
+        }
+    }
+
+    /// Records that the instance with the given slot released its access, which can happen on another thread.
+    #[inline]
+    pub(super) fn released(&self, _index: usize) {
+        #[cfg(feature = "deadlock-detection")]
+        if let Some(detector) = lock(&DETECTOR).as_mut() {
+            detector.held.remove(&(self.id, _index));
+        }
+    }
+
+    #[cfg(feature = "deadlock-detection")]
+    fn check_slow(&self, access: Access) {
+        let current = Backtrace::force_capture();
+        let thread = thread::current().id();
+
+        let mut guard = lock(&DETECTOR);
+        let detector = guard.get_or_insert_with(Detector::default);
+
+        let mut report = None;
+        let mut edges = Vec::new();
+        for (&(id, _), held) in detector.held.iter().filter(|(_, held)| held.thread == thread) {
+            if id == self.id {
+                // A writer waits for all readers, and readers wait for the writer, even within the same instance.
+                if held.access == Access::Write || access == Access::Write {
+                    report = Some(format!("Deadlock: this thread acquires {access:?} access to a shared mutex, while \
+                        it already holds {:?} access to it.\n\nThe held access was acquired at:\n{}\n\n\
+                        The new access is acquired at:\n{current}", held.access, held.backtrace));
+                    break;
+                }
+
+                continue;
+            }
+
+            // Readers of different shared mutexes are ordered as well, since a writer can block either of them.
+            if let Some(edge) = detector.path(self.id, id) {
+                report = Some(format!("Potential deadlock: this thread acquires two shared mutexes in the opposite \
+                    order of another acquisition.\n\nThe held shared mutex was acquired at:\n{}\n\nThe other shared \
+                    mutex is acquired at:\n{current}\n\nPreviously, a shared mutex was held at:\n{}\n\nwhile acquiring \
+                    another one at:\n{}", held.backtrace, edge.held, edge.acquired));
+                break;
+            }
+
+            edges.push((id, held.backtrace.clone()));
+        }
+
+        if let Some(report) = report {
+            // Release the detector first, so that other threads can continue.
+            drop(guard);
+            panic!("{report}");
+        }
+
+        let current = Arc::new(current);
+        for (id, held) in edges {
+            detector.order.entry((id, self.id)).or_insert_with(|| Edge { held, acquired: current.clone() });
+        }
+    }
+}
+
+#[cfg(feature = "deadlock-detection")]
+impl Drop for LockId {
+    fn drop(&mut self) {
+        if let Some(detector) = lock(&DETECTOR).as_mut() {
+            detector.held.retain(|&(id, _), _| id != self.id);
+            detector.order.retain(|&(from, to), _| from != self.id && to != self.id);
+        }
+    }
+}
+
+#[cfg(feature = "deadlock-detection")]
+impl Detector {
+
+    /// Returns the first edge of a path in the acquisition order from one shared mutex to another.
+    fn path(&self, from: usize, to: usize) -> Option<&Edge> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<(usize, Option<&Edge>)> = vec![(from, None)];
+
+        while let Some((id, first)) = stack.pop() {
+            if id == to {
+                return first;
+            }
+
+            if !visited.insert(id) {
+                continue;
+            }
+
+            for (&(_, next), edge) in self.order.iter().filter(|(&(source, _), _)| source == id) {
+                stack.push((next, first.or(Some(edge))));
+            }
+        }
+
+        None
+    }
+}
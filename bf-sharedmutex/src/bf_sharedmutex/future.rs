@@ -0,0 +1,297 @@
+use core::{future::Future, pin::Pin, sync::atomic::Ordering, task::{ready, Context, Poll}};
+
+#[cfg(not(loom))]
+use core::marker::PhantomData;
+
+use crate::{LockResult, waiter::{lock, Waiter}};
+
+use super::{BfSharedMutex, BfSharedMutexReadGuard, BfSharedMutexWriteGuard};
+
+#[cfg(not(loom))]
+use super::{BfSharedMutexOwnedReadGuard, BfSharedMutexOwnedWriteGuard};
+
+/// The future returned by [BfSharedMutex::read_async], resolves to a read guard once no writer is active.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BfSharedMutexReadFuture<'a, T> {
+    mutex: &'a BfSharedMutex<T>,
+
+    /// Set while this reader is counted as blocked by a writer.
+    blocked: bool,
+}
+
+/// The future returned by [BfSharedMutex::write_async], resolves to a write guard once all readers have left.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BfSharedMutexWriteFuture<'a, T> {
+    mutex: &'a BfSharedMutex<T>,
+    state: WriteState,
+}
+
+/// The future returned by [BfSharedMutex::read_owned_async], resolves to an owned read guard once no writer is active.
+#[cfg(not(loom))]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BfSharedMutexOwnedReadFuture<T> {
+    /// The instance is moved into the guard once the future resolves.
+    mutex: Option<BfSharedMutex<T>>,
+
+    /// Set while this reader is counted as blocked by a writer.
+    blocked: bool,
+
+    /// The future is only sent to other threads when the object can be shared, see the Send implementation.
+    marker: PhantomData<*const T>,
+}
+
+/// The future returned by [BfSharedMutex::write_owned_async], resolves to an owned write guard once all readers have
+/// left.
+#[cfg(not(loom))]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BfSharedMutexOwnedWriteFuture<T> {
+    /// The instance is moved into the guard once the future resolves.
+    mutex: Option<BfSharedMutex<T>>,
+    state: WriteState,
+    marker: PhantomData<*const T>,
+}
+
+// Like the owned guards, the futures can resolve on another thread while other instances access the object.
+#[cfg(not(loom))]
+unsafe impl<T: Send + Sync> Send for BfSharedMutexOwnedReadFuture<T> {}
+#[cfg(not(loom))]
+unsafe impl<T: Send + Sync> Send for BfSharedMutexOwnedWriteFuture<T> {}
+
+/// The progress of a write future. The upgrade lock is held while waiting and draining, but its guard is forgotten so
+/// that the state does not borrow the instance. It is released by the write guard, or when the future is dropped.
+enum WriteState {
+    /// Not yet polled.
+    Idle,
+
+    /// Queued as writer, waiting for the other writers and the upgradable reader.
+    Locking,
+
+    /// Holding the upgrade lock, waiting until the fairness policy allows us to forbid access.
+    Waiting,
+
+    /// Holding the upgrade lock, all instances are forbidden and we wait for the readers to leave.
+    Draining,
+
+    /// The write guard has been returned.
+    Done,
+}
+
+impl<T> BfSharedMutex<T> {
+
+    /// Provides read access to the underlying object like [BfSharedMutex::read], but instead of parking the
+    /// thread the task is woken up by the writer. Works with any executor, but the future borrows this instance so
+    /// it cannot be sent to another thread, see [BfSharedMutex::read_owned_async] for multi-threaded executors.
+    pub fn read_async(&self) -> BfSharedMutexReadFuture<'_, T> {
+        BfSharedMutexReadFuture { mutex: self, blocked: false }
+    }
+
+    /// Provides write access to the underlying object like [BfSharedMutex::write], but instead of parking the
+    /// thread the task is woken up by the readers. Dropping the future before it resolves allows the readers again.
+    pub fn write_async(&self) -> BfSharedMutexWriteFuture<'_, T> {
+        BfSharedMutexWriteFuture { mutex: self, state: WriteState::Idle }
+    }
+
+    /// Provides read access like [BfSharedMutex::read_async], but the future takes ownership of this instance and
+    /// resolves to the guard of [BfSharedMutex::read_owned]. The future is Send, so it can be spawned on a
+    /// multi-threaded executor.
+    #[cfg(not(loom))]
+    pub fn read_owned_async(self) -> BfSharedMutexOwnedReadFuture<T> {
+        BfSharedMutexOwnedReadFuture { mutex: Some(self), blocked: false, marker: PhantomData }
+    }
+
+    /// Provides write access like [BfSharedMutex::write_async], but the future takes ownership of this instance and
+    /// resolves to the guard of [BfSharedMutex::write_owned]. The future is Send, so it can be spawned on a
+    /// multi-threaded executor.
+    #[cfg(not(loom))]
+    pub fn write_owned_async(self) -> BfSharedMutexOwnedWriteFuture<T> {
+        BfSharedMutexOwnedWriteFuture { mutex: Some(self), state: WriteState::Idle, marker: PhantomData }
+    }
+
+    /// Enters the reader section once no writer is active, otherwise the task is woken up by the writer.
+    fn poll_read(&self, blocked: &mut bool, cx: &mut Context<'_>) -> Poll<()> {
+        // Inside a nested section the busy flag is already set, which is enough to keep writers out.
+        if self.depth.get() == 0 {
+            self.control().busy.store(true, Ordering::SeqCst);
+            while self.control().forbidden.load(Ordering::SeqCst) {
+                self.clear_busy();
+
+                if !*blocked {
+                    self.control().counters.forbidden_read();
+                    self.shared.blocked.fetch_add(1, Ordering::SeqCst);
+                    *blocked = true;
+                }
+
+                // Register before checking the forbidden flag again, so that the writer is guaranteed to wake us up.
+                self.register_reader(Waiter::Task(cx.waker().clone()));
+                if self.control().forbidden.load(Ordering::SeqCst) {
+                    return Poll::Pending;
+                }
+
+                self.control().busy.store(true, Ordering::SeqCst);
+            }
+        }
+
+        if *blocked {
+            self.shared.unblock();
+            *blocked = false;
+        }
+
+        Poll::Ready(())
+    }
+}
+
+impl WriteState {
+
+    /// Advances the writer, returns ready once all readers have left. The caller then holds the upgrade lock without
+    /// a guard, and must construct the write guard.
+    fn poll<T>(&mut self, mutex: &BfSharedMutex<T>, cx: &mut Context<'_>) -> Poll<()> {
+        if let WriteState::Idle = self {
+            debug_assert!(!mutex.control().busy.load(Ordering::SeqCst),
+                "Can only exclusive lock outside of a shared lock, no upgrading!");
+
+            lock(&mutex.shared.other).queued += 1;
+            *self = WriteState::Locking;
+        }
+
+        if let WriteState::Locking = self {
+            let upgrade = ready!(mutex.shared.upgrade.poll_lock(cx));
+            lock(&mutex.shared.other).dequeue(true);
+
+            // The upgrade lock is released by the write guard, or when the future is dropped.
+            core::mem::forget(upgrade);
+            *self = WriteState::Waiting;
+        }
+
+        if let WriteState::Waiting = self {
+            if !mutex.shared.may_forbid() {
+                // Register before checking the policy again, so that the readers are guaranteed to wake us up.
+                mutex.shared.register_pending(Waiter::Task(cx.waker().clone()));
+                if !mutex.shared.may_forbid() {
+                    return Poll::Pending;
+                }
+            }
+
+            mutex.shared.clear_pending();
+
+            // Make all instances wait due to forbidden access.
+            lock(&mutex.shared.other).forbid();
+            *self = WriteState::Draining;
+        }
+
+        match self {
+            WriteState::Draining => {
+                ready!(mutex.shared.poll_readers(cx));
+                *self = WriteState::Done;
+                Poll::Ready(())
+            }
+            _ => panic!("The write future was polled after completion"),
+        }
+    }
+
+    /// Rolls back the progress of a future that is dropped before it resolved.
+    fn cancel<T>(&self, mutex: &BfSharedMutex<T>) {
+        let shared = &mutex.shared;
+
+        match self {
+            WriteState::Locking => lock(&shared.other).dequeue(false),
+            WriteState::Waiting => {
+                shared.clear_pending();
+                lock(&shared.other).release(shared.policy);
+                unsafe { shared.upgrade.force_unlock() };
+            }
+            WriteState::Draining => {
+                // Stop waiting for the readers and allow them again, before releasing the upgrade lock.
+                *lock(&shared.writer) = None;
+                lock(&shared.other).release(shared.policy);
+                unsafe { shared.upgrade.force_unlock() };
+            }
+            WriteState::Idle | WriteState::Done => {}
+        }
+    }
+}
+
+impl<'a, T> Future for BfSharedMutexReadFuture<'a, T> {
+    type Output = LockResult<BfSharedMutexReadGuard<'a, T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        ready!(this.mutex.poll_read(&mut this.blocked, cx));
+
+        Poll::Ready(this.mutex.poison(this.mutex.read_guard()))
+    }
+}
+
+impl<T> Drop for BfSharedMutexReadFuture<'_, T> {
+    fn drop(&mut self) {
+        if self.blocked {
+            self.mutex.shared.unblock();
+        }
+    }
+}
+
+impl<'a, T> Future for BfSharedMutexWriteFuture<'a, T> {
+    type Output = LockResult<BfSharedMutexWriteGuard<'a, T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        ready!(this.state.poll(this.mutex, cx));
+
+        // The guard takes over the upgrade lock that was acquired by the state.
+        let upgrade = unsafe { this.mutex.shared.upgrade.guard() };
+        Poll::Ready(this.mutex.poison(this.mutex.write_guard(upgrade)))
+    }
+}
+
+impl<T> Drop for BfSharedMutexWriteFuture<'_, T> {
+    fn drop(&mut self) {
+        self.state.cancel(self.mutex);
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Future for BfSharedMutexOwnedReadFuture<T> {
+    type Output = LockResult<BfSharedMutexOwnedReadGuard<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mutex = this.mutex.as_ref().expect("The read future was polled after completion");
+        ready!(mutex.poll_read(&mut this.blocked, cx));
+
+        let mutex = this.mutex.take().expect("The instance is only taken once");
+        Poll::Ready(mutex.owned_read_guard())
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Drop for BfSharedMutexOwnedReadFuture<T> {
+    fn drop(&mut self) {
+        if let Some(mutex) = &self.mutex {
+            if self.blocked {
+                mutex.shared.unblock();
+            }
+        }
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Future for BfSharedMutexOwnedWriteFuture<T> {
+    type Output = LockResult<BfSharedMutexOwnedWriteGuard<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let mutex = this.mutex.as_ref().expect("The write future was polled after completion");
+        ready!(this.state.poll(mutex, cx));
+
+        let mutex = this.mutex.take().expect("The instance is only taken once");
+        Poll::Ready(mutex.owned_write_guard())
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Drop for BfSharedMutexOwnedWriteFuture<T> {
+    fn drop(&mut self) {
+        if let Some(mutex) = &self.mutex {
+            self.state.cancel(mutex);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use core::{marker::PhantomData, mem::ManuallyDrop, ops::{Deref, DerefMut}, ptr::{self, NonNull}};
+
+use crate::waiter::ExclusiveGuard;
+
+use super::{BfSharedMutex, BfSharedMutexReadGuard, BfSharedMutexWriteGuard};
+
+/// A read guard that dereferences to a part of the protected object, obtained by [BfSharedMutexReadGuard::map].
+#[must_use = "Dropping the guard unlocks the shared mutex immediately"]
+pub struct BfSharedMutexMappedReadGuard<'a, T, U: ?Sized> {
+    mutex: &'a BfSharedMutex<T>,
+    data: NonNull<U>,
+    marker: PhantomData<&'a U>,
+
+    #[cfg(loom)]
+    _access: loom::cell::ConstPtr<T>,
+}
+
+/// A write guard that dereferences to a part of the protected object, obtained by [BfSharedMutexWriteGuard::map].
+#[must_use = "Dropping the guard unlocks the shared mutex immediately"]
+pub struct BfSharedMutexMappedWriteGuard<'a, T, U: ?Sized> {
+    mutex: &'a BfSharedMutex<T>,
+    data: NonNull<U>,
+    marker: PhantomData<&'a mut U>,
+    _upgrade: ExclusiveGuard<'a>,
+
+    /// Whether the thread was already panicking when the guard was acquired.
+    panicking: bool,
+
+    #[cfg(loom)]
+    _access: loom::cell::MutPtr<T>,
+}
+
+impl<'a, T> BfSharedMutexReadGuard<'a, T> {
+
+    /// Makes a guard for a part of the protected object, for example one of its fields. This is an associated
+    /// function, called as `BfSharedMutexReadGuard::map(guard, ...)`, so that it does not shadow methods of `T`.
+    pub fn map<U: ?Sized, F>(guard: Self, f: F) -> BfSharedMutexMappedReadGuard<'a, T, U>
+    where
+        F: FnOnce(&T) -> &U,
+    {
+        let data = NonNull::from(f(&*guard));
+        Self::into_mapped(guard, data)
+    }
+
+    /// Makes a guard for a part of the protected object like [BfSharedMutexReadGuard::map], but returns the original
+    /// guard together with the error when the closure fails.
+    pub fn try_map<U: ?Sized, E, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedReadGuard<'a, T, U>, (Self, E)>
+    where
+        F: FnOnce(&T) -> Result<&U, E>,
+    {
+        // The reference does not borrow the guard, so the guard can still be returned on failure.
+        let object: *const T = &*guard;
+        match f(unsafe { &*object }) {
+            Ok(data) => Ok(Self::into_mapped(guard, NonNull::from(data))),
+            Err(error) => Err((guard, error)),
+        }
+    }
+
+    /// Makes a guard for a part of the protected object like [BfSharedMutexReadGuard::map], but returns the original
+    /// guard when the closure returns None.
+    pub fn filter_map<U: ?Sized, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedReadGuard<'a, T, U>, Self>
+    where
+        F: FnOnce(&T) -> Option<&U>,
+    {
+        Self::try_map(guard, |object| f(object).ok_or(())).map_err(|(guard, _)| guard)
+    }
+
+    /// Moves the reader section into a mapped guard.
+    fn into_mapped<U: ?Sized>(guard: Self, data: NonNull<U>) -> BfSharedMutexMappedReadGuard<'a, T, U> {
+        let guard = ManuallyDrop::new(guard);
+
+        BfSharedMutexMappedReadGuard {
+            mutex: guard.mutex,
+            data,
+            marker: PhantomData,
+            #[cfg(loom)]
+            _access: unsafe { ptr::read(&guard.access) },
+        }
+    }
+}
+
+impl<'a, T, U: ?Sized> BfSharedMutexMappedReadGuard<'a, T, U> {
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexReadGuard::map].
+    pub fn map<V: ?Sized, F>(guard: Self, f: F) -> BfSharedMutexMappedReadGuard<'a, T, V>
+    where
+        F: FnOnce(&U) -> &V,
+    {
+        let data = NonNull::from(f(&*guard));
+        Self::into_mapped(guard, data)
+    }
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexReadGuard::try_map].
+    pub fn try_map<V: ?Sized, E, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedReadGuard<'a, T, V>, (Self, E)>
+    where
+        F: FnOnce(&U) -> Result<&V, E>,
+    {
+        match f(unsafe { guard.data.as_ref() }) {
+            Ok(data) => Ok(Self::into_mapped(guard, NonNull::from(data))),
+            Err(error) => Err((guard, error)),
+        }
+    }
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexReadGuard::filter_map].
+    pub fn filter_map<V: ?Sized, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedReadGuard<'a, T, V>, Self>
+    where
+        F: FnOnce(&U) -> Option<&V>,
+    {
+        Self::try_map(guard, |object| f(object).ok_or(())).map_err(|(guard, _)| guard)
+    }
+
+    fn into_mapped<V: ?Sized>(guard: Self, data: NonNull<V>) -> BfSharedMutexMappedReadGuard<'a, T, V> {
+        let guard = ManuallyDrop::new(guard);
+
+        BfSharedMutexMappedReadGuard {
+            mutex: guard.mutex,
+            data,
+            marker: PhantomData,
+            #[cfg(loom)]
+            _access: unsafe { ptr::read(&guard._access) },
+        }
+    }
+}
+
+impl<'a, T> BfSharedMutexWriteGuard<'a, T> {
+
+    /// Makes a guard for a part of the protected object, for example one of its fields. This is an associated
+    /// function, called as `BfSharedMutexWriteGuard::map(guard, ...)`, so that it does not shadow methods of `T`.
+    pub fn map<U: ?Sized, F>(mut guard: Self, f: F) -> BfSharedMutexMappedWriteGuard<'a, T, U>
+    where
+        F: FnOnce(&mut T) -> &mut U,
+    {
+        let data = NonNull::from(f(&mut *guard));
+        Self::into_mapped(guard, data)
+    }
+
+    /// Makes a guard for a part of the protected object like [BfSharedMutexWriteGuard::map], but returns the original
+    /// guard together with the error when the closure fails.
+    pub fn try_map<U: ?Sized, E, F>(mut guard: Self, f: F) -> Result<BfSharedMutexMappedWriteGuard<'a, T, U>, (Self, E)>
+    where
+        F: FnOnce(&mut T) -> Result<&mut U, E>,
+    {
+        // The reference does not borrow the guard, so the guard can still be returned on failure.
+        let object: *mut T = &mut *guard;
+        match f(unsafe { &mut *object }) {
+            Ok(data) => Ok(Self::into_mapped(guard, NonNull::from(data))),
+            Err(error) => Err((guard, error)),
+        }
+    }
+
+    /// Makes a guard for a part of the protected object like [BfSharedMutexWriteGuard::map], but returns the original
+    /// guard when the closure returns None.
+    pub fn filter_map<U: ?Sized, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedWriteGuard<'a, T, U>, Self>
+    where
+        F: FnOnce(&mut T) -> Option<&mut U>,
+    {
+        Self::try_map(guard, |object| f(object).ok_or(())).map_err(|(guard, _)| guard)
+    }
+
+    /// Moves the exclusive section into a mapped guard.
+    fn into_mapped<U: ?Sized>(guard: Self, data: NonNull<U>) -> BfSharedMutexMappedWriteGuard<'a, T, U> {
+        let guard = ManuallyDrop::new(guard);
+
+        BfSharedMutexMappedWriteGuard {
+            mutex: guard.mutex,
+            data,
+            marker: PhantomData,
+            _upgrade: unsafe { ptr::read(&guard._upgrade) },
+            panicking: guard.panicking,
+            #[cfg(loom)]
+            _access: unsafe { ptr::read(&guard.access) },
+        }
+    }
+}
+
+impl<'a, T, U: ?Sized> BfSharedMutexMappedWriteGuard<'a, T, U> {
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexWriteGuard::map].
+    pub fn map<V: ?Sized, F>(mut guard: Self, f: F) -> BfSharedMutexMappedWriteGuard<'a, T, V>
+    where
+        F: FnOnce(&mut U) -> &mut V,
+    {
+        let data = NonNull::from(f(&mut *guard));
+        Self::into_mapped(guard, data)
+    }
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexWriteGuard::try_map].
+    pub fn try_map<V: ?Sized, E, F>(mut guard: Self, f: F) -> Result<BfSharedMutexMappedWriteGuard<'a, T, V>, (Self, E)>
+    where
+        F: FnOnce(&mut U) -> Result<&mut V, E>,
+    {
+        match f(unsafe { guard.data.as_mut() }) {
+            Ok(data) => Ok(Self::into_mapped(guard, NonNull::from(data))),
+            Err(error) => Err((guard, error)),
+        }
+    }
+
+    /// Makes a guard for a part of the already mapped object, see [BfSharedMutexWriteGuard::filter_map].
+    pub fn filter_map<V: ?Sized, F>(guard: Self, f: F) -> Result<BfSharedMutexMappedWriteGuard<'a, T, V>, Self>
+    where
+        F: FnOnce(&mut U) -> Option<&mut V>,
+    {
+        Self::try_map(guard, |object| f(object).ok_or(())).map_err(|(guard, _)| guard)
+    }
+
+    fn into_mapped<V: ?Sized>(guard: Self, data: NonNull<V>) -> BfSharedMutexMappedWriteGuard<'a, T, V> {
+        let guard = ManuallyDrop::new(guard);
+
+        BfSharedMutexMappedWriteGuard {
+            mutex: guard.mutex,
+            data,
+            marker: PhantomData,
+            _upgrade: unsafe { ptr::read(&guard._upgrade) },
+            panicking: guard.panicking,
+            #[cfg(loom)]
+            _access: unsafe { ptr::read(&guard._access) },
+        }
+    }
+}
+
+impl<T, U: ?Sized> Deref for BfSharedMutexMappedReadGuard<'_, T, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        // The reader section is still active, so the part of the object can only be accessed immutably.
+        unsafe { self.data.as_ref() }
+    }
+}
+
+impl<T, U: ?Sized> Drop for BfSharedMutexMappedReadGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.mutex.exit_reader();
+    }
+}
+
+impl<T, U: ?Sized> Deref for BfSharedMutexMappedWriteGuard<'_, T, U> {
+    type Target = U;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.data.as_ref() }
+    }
+}
+
+impl<T, U: ?Sized> DerefMut for BfSharedMutexMappedWriteGuard<'_, T, U> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // The exclusive section is still active, so we have the only reference to this part of the object.
+        unsafe { self.data.as_mut() }
+    }
+}
+
+impl<T, U: ?Sized> Drop for BfSharedMutexMappedWriteGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.mutex.exit_writer(self.panicking);
+
+        // The upgrade lock is then released here.
+    }
+}
@@ -0,0 +1,200 @@
+use core::{marker::PhantomData, mem, ops::{Deref, DerefMut}};
+
+#[cfg(loom)]
+use core::ptr;
+
+use crate::{LockResult, PoisonError};
+
+use super::BfSharedMutex;
+
+/// The guard object for shared access that owns its instance of the shared mutex, obtained by
+/// [BfSharedMutex::read_owned]. It has no lifetime, so it can for example be stored in a struct.
+#[must_use = "Dropping the guard unlocks the shared mutex immediately"]
+pub struct BfSharedMutexOwnedReadGuard<T> {
+    mutex: BfSharedMutex<T>,
+
+    /// The guard is only sent to other threads when the object can be shared, see the Send implementation.
+    marker: PhantomData<*const T>,
+
+    #[cfg(loom)]
+    access: loom::cell::ConstPtr<T>,
+}
+
+/// The guard object for exclusive access that owns its instance of the shared mutex, obtained by
+/// [BfSharedMutex::write_owned].
+#[must_use = "Dropping the guard unlocks the shared mutex immediately"]
+pub struct BfSharedMutexOwnedWriteGuard<T> {
+    mutex: BfSharedMutex<T>,
+
+    /// Whether the thread was already panicking when the guard was acquired.
+    panicking: bool,
+
+    marker: PhantomData<*const T>,
+
+    #[cfg(loom)]
+    access: loom::cell::MutPtr<T>,
+}
+
+// The object is accessed from the thread that the guard is sent to, while other instances can access it concurrently.
+unsafe impl<T: Send + Sync> Send for BfSharedMutexOwnedReadGuard<T> {}
+unsafe impl<T: Send + Sync> Send for BfSharedMutexOwnedWriteGuard<T> {}
+
+impl<T> BfSharedMutex<T> {
+
+    /// Provides read access like [BfSharedMutex::read], but the guard takes ownership of this instance instead of
+    /// borrowing it. Clone the instance first to keep using it.
+    pub fn read_owned(self) -> LockResult<BfSharedMutexOwnedReadGuard<T>> {
+        let guard = self.read().unwrap_or_else(PoisonError::into_inner);
+
+        // The reader section is left when the owned guard is dropped instead.
+        #[cfg(loom)]
+        let access = unsafe { ptr::read(&guard.access) };
+        mem::forget(guard);
+
+        let guard = BfSharedMutexOwnedReadGuard {
+            mutex: self,
+            marker: PhantomData,
+            #[cfg(loom)]
+            access,
+        };
+
+        if guard.mutex.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Provides write access like [BfSharedMutex::write], but the guard takes ownership of this instance instead of
+    /// borrowing it. Clone the instance first to keep using it.
+    pub fn write_owned(self) -> LockResult<BfSharedMutexOwnedWriteGuard<T>> {
+        let guard = self.write().unwrap_or_else(PoisonError::into_inner);
+
+        // The upgrade lock is released when the owned guard is dropped instead.
+        let panicking = guard.panicking;
+        #[cfg(loom)]
+        let access = unsafe { ptr::read(&guard.access) };
+        mem::forget(guard);
+
+        let guard = BfSharedMutexOwnedWriteGuard {
+            mutex: self,
+            panicking,
+            marker: PhantomData,
+            #[cfg(loom)]
+            access,
+        };
+
+        if guard.mutex.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Constructs the owned read guard, the caller must have acquired shared access according to the protocol.
+    #[cfg(not(loom))]
+    pub(super) fn owned_read_guard(self) -> LockResult<BfSharedMutexOwnedReadGuard<T>> {
+        // The reader section is left when the owned guard is dropped instead.
+        mem::forget(self.read_guard());
+
+        let guard = BfSharedMutexOwnedReadGuard {
+            mutex: self,
+            marker: PhantomData,
+        };
+
+        if guard.mutex.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /// Constructs the owned write guard, the caller must hold the upgrade lock without a guard and have acquired
+    /// exclusive access according to the protocol.
+    #[cfg(not(loom))]
+    pub(super) fn owned_write_guard(self) -> LockResult<BfSharedMutexOwnedWriteGuard<T>> {
+        // The upgrade lock is released when the owned guard is dropped instead.
+        let guard = self.write_guard(unsafe { self.shared.upgrade.guard() });
+        let panicking = guard.panicking;
+        mem::forget(guard);
+
+        let guard = BfSharedMutexOwnedWriteGuard {
+            mutex: self,
+            panicking,
+            marker: PhantomData,
+        };
+
+        if guard.mutex.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Deref for BfSharedMutexOwnedReadGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // There can only be shared guards, which only provide immutable access to the object.
+        unsafe { &*self.mutex.shared.object.get() }
+    }
+}
+
+#[cfg(loom)]
+impl<T> Deref for BfSharedMutexOwnedReadGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.access.deref() }
+    }
+}
+
+impl<T> Drop for BfSharedMutexOwnedReadGuard<T> {
+    fn drop(&mut self) {
+        self.mutex.exit_reader();
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> Deref for BfSharedMutexOwnedWriteGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.shared.object.get() }
+    }
+}
+
+#[cfg(not(loom))]
+impl<T> DerefMut for BfSharedMutexOwnedWriteGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // We hold the exclusive section, so we can provide mutable access to the underlying object.
+        unsafe { &mut *self.mutex.shared.object.get() }
+    }
+}
+
+#[cfg(loom)]
+impl<T> Deref for BfSharedMutexOwnedWriteGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.access.deref() }
+    }
+}
+
+#[cfg(loom)]
+impl<T> DerefMut for BfSharedMutexOwnedWriteGuard<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.access.deref() }
+    }
+}
+
+impl<T> Drop for BfSharedMutexOwnedWriteGuard<T> {
+    fn drop(&mut self) {
+        self.mutex.exit_writer(self.panicking);
+
+        // The guard of the upgrade lock was forgotten when this guard was constructed.
+        unsafe { self.mutex.shared.upgrade.force_unlock() };
+    }
+}
@@ -0,0 +1,144 @@
+use std::{mem::{self, ManuallyDrop}, sync::{atomic::Ordering, OnceLock}};
+
+use lock_api::{GuardNoSend, RawRwLock, RawRwLockDowngrade, RawRwLockUpgrade};
+
+use crate::{waiter::lock, PoisonError, TryLockError};
+
+use super::{deadlock::Access, BfRwLock, BfSharedMutex};
+
+/// The busy-forbidden protocol as a [lock_api::RawRwLock], so that it can be used by generic code through
+/// [lock_api::RwLock]. Like [BfRwLock] every thread uses its own instance of the shared mutex, which is created when
+/// the thread first acquires the lock.
+///
+/// The lock does not track poisoning, a panic during the exclusive section is ignored like [lock_api] does.
+pub struct RawBfRwLock {
+    /// Created on first use, since the shared data of the protocol cannot be constructed in a constant.
+    lock: OnceLock<BfRwLock<()>>,
+}
+
+/// A [lock_api::RwLock] that uses the busy-forbidden protocol.
+pub type BfLockApiRwLock<T> = lock_api::RwLock<RawBfRwLock, T>;
+
+impl RawBfRwLock {
+
+    /// Returns the instance of the current thread, every guard is released on the thread that acquired it.
+    pub(super) fn handle(&self) -> &BfSharedMutex<ManuallyDrop<()>> {
+        self.lock.get_or_init(BfRwLock::default).handle()
+    }
+}
+
+unsafe impl RawRwLock for RawBfRwLock {
+    const INIT: Self = RawBfRwLock { lock: OnceLock::new() };
+
+    // The guards must be released on the thread that acquired them, since that thread owns the instance.
+    type GuardMarker = GuardNoSend;
+
+    fn lock_shared(&self) {
+        mem::forget(self.handle().read().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    fn try_lock_shared(&self) -> bool {
+        match self.handle().try_read() {
+            Ok(guard) => mem::forget(guard),
+            Err(TryLockError::Poisoned(error)) => mem::forget(error.into_inner()),
+            Err(_) => return false,
+        }
+
+        true
+    }
+
+    unsafe fn unlock_shared(&self) {
+        self.handle().exit_reader();
+    }
+
+    fn lock_exclusive(&self) {
+        mem::forget(self.handle().write().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    fn try_lock_exclusive(&self) -> bool {
+        match self.handle().try_write() {
+            Ok(guard) => mem::forget(guard),
+            Err(TryLockError::Poisoned(error)) => mem::forget(error.into_inner()),
+            Err(_) => return false,
+        }
+
+        true
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        let handle = self.handle();
+
+        // Pretend that we were already panicking, so that the lock is never poisoned.
+        handle.exit_writer(true);
+        handle.shared.upgrade.force_unlock();
+    }
+
+    fn is_locked(&self) -> bool {
+        // The default implementation tries to acquire write access, which is not allowed inside a reader section.
+        let other = lock(&self.handle().shared.other);
+        other.writing || other.any_busy()
+    }
+}
+
+unsafe impl RawRwLockUpgrade for RawBfRwLock {
+    fn lock_upgradable(&self) {
+        mem::forget(self.handle().upgradable_read().unwrap_or_else(PoisonError::into_inner));
+    }
+
+    fn try_lock_upgradable(&self) -> bool {
+        match self.handle().try_upgradable_read() {
+            Ok(guard) => mem::forget(guard),
+            Err(TryLockError::Poisoned(error)) => mem::forget(error.into_inner()),
+            Err(_) => return false,
+        }
+
+        true
+    }
+
+    unsafe fn unlock_upgradable(&self) {
+        let handle = self.handle();
+        handle.exit_reader();
+        handle.shared.upgrade.force_unlock();
+    }
+
+    unsafe fn upgrade(&self) {
+        let handle = self.handle();
+        handle.upgrade_reader();
+        handle.control().counters.write();
+        handle.shared.id.held(handle.index, Access::Write);
+    }
+
+    unsafe fn try_upgrade(&self) -> bool {
+        let handle = self.handle();
+        assert_eq!(handle.depth.get(), 1, "Cannot upgrade while other read guards of this instance exist");
+
+        // The registry is only held briefly, for example by a thread that drops its instance, so we wait for it.
+        let mut other = lock(&handle.shared.other);
+
+        // Holding the upgrade lock excludes the writers, so we can leave our reader section temporarily.
+        handle.control().busy.store(false, Ordering::SeqCst);
+        other.forbid();
+
+        // Readers that are still busy would make us wait, so undo the forbidden flags instead.
+        if other.any_busy() {
+            other.release(handle.shared.policy);
+            handle.control().busy.store(true, Ordering::SeqCst);
+            return false;
+        }
+
+        handle.depth.set(0);
+        handle.control().counters.write();
+        handle.shared.id.released(handle.index);
+        handle.shared.id.held(handle.index, Access::Write);
+        true
+    }
+}
+
+unsafe impl RawRwLockDowngrade for RawBfRwLock {
+    unsafe fn downgrade(&self) {
+        let handle = self.handle();
+        handle.downgrade_writer();
+        handle.shared.upgrade.force_unlock();
+        mem::forget(handle.read_guard());
+    }
+}
@@ -0,0 +1,165 @@
+use std::{cell::RefCell, marker::PhantomData, mem::ManuallyDrop, ptr::NonNull, sync::{Arc, Weak}};
+
+use crate::{LockResult, TryLockResult};
+
+use super::{BfSharedMutex, BfSharedMutexMappedReadGuard, BfSharedMutexMappedWriteGuard, BfSharedMutexReadGuard, BfSharedMutexWriteGuard};
+
+/// A readers-writer lock that can be shared between threads like [std::sync::RwLock]. Every thread that accesses
+/// the lock uses its own [BfSharedMutex] instance, which is created on first use and dropped when the thread exits.
+///
+/// The read sections of a thread can be nested, but a thread cannot acquire write access inside a read section.
+///
+/// Like [std::sync::RwLock] the lock can only be sent to another thread when the object can, so this does not compile:
+///
+/// ```compile_fail
+/// let lock = bf_sharedmutex::BfRwLock::new(std::rc::Rc::new(0));
+/// std::thread::spawn(move || drop(lock));
+/// ```
+pub struct BfRwLock<T> {
+    /// The instance from which the instances of the threads are cloned, it is never used to acquire access.
+    mutex: BfSharedMutex<ManuallyDrop<T>>,
+
+    /// Identifies this lock in the caches of the threads, which only keep a weak reference to it.
+    token: Arc<()>,
+
+    /// The instance is Send for any object, so the lock opts out of the automatic implementations, see below.
+    marker: PhantomData<*const T>,
+}
+
+/// The guard object for shared access to the object protected by a [BfRwLock].
+pub type BfRwLockReadGuard<'a, T> = BfSharedMutexMappedReadGuard<'a, ManuallyDrop<T>, T>;
+
+/// The guard object for exclusive access to the object protected by a [BfRwLock].
+pub type BfRwLockWriteGuard<'a, T> = BfSharedMutexMappedWriteGuard<'a, ManuallyDrop<T>, T>;
+
+// Like a standard RwLock, moving the lock to another thread moves the object along with it.
+unsafe impl<T: Send> Send for BfRwLock<T> {}
+
+// The instance of a thread is only used by that thread, the shared instance is only cloned.
+unsafe impl<T: Send + Sync> Sync for BfRwLock<T> {}
+
+/// The instance of one of the locks that the current thread has accessed.
+struct CachedHandle {
+    token: Weak<()>,
+    handle: NonNull<()>,
+    drop: unsafe fn(NonNull<()>),
+}
+
+thread_local! {
+    static HANDLES: RefCell<Vec<CachedHandle>> = const { RefCell::new(Vec::new()) };
+}
+
+impl<T> BfRwLock<T> {
+
+    /// Constructs a new lock for protecting access to the given object.
+    pub fn new(object: T) -> Self {
+        Self {
+            mutex: BfSharedMutex::new(ManuallyDrop::new(object)),
+            token: Arc::new(()),
+            marker: PhantomData,
+        }
+    }
+
+    /// Provides read access to the underlying object, see [BfSharedMutex::read].
+    pub fn read(&self) -> LockResult<BfRwLockReadGuard<'_, T>> {
+        self.handle().read().map(map_read).map_err(|error| error.map(map_read))
+    }
+
+    /// Attempts to acquire read access without blocking, see [BfSharedMutex::try_read].
+    pub fn try_read(&self) -> TryLockResult<BfRwLockReadGuard<'_, T>> {
+        self.handle().try_read().map(map_read).map_err(|error| error.map(map_read))
+    }
+
+    /// Provides write access to the underlying object, see [BfSharedMutex::write].
+    pub fn write(&self) -> LockResult<BfRwLockWriteGuard<'_, T>> {
+        self.handle().write().map(map_write).map_err(|error| error.map(map_write))
+    }
+
+    /// Attempts to acquire write access without blocking, see [BfSharedMutex::try_write].
+    pub fn try_write(&self) -> TryLockResult<BfRwLockWriteGuard<'_, T>> {
+        self.handle().try_write().map(map_write).map_err(|error| error.map(map_write))
+    }
+
+    /// Returns true iff a thread panicked while it had write access.
+    pub fn is_poisoned(&self) -> bool {
+        self.mutex.is_poisoned()
+    }
+
+    /// Obtain mutable access to the object without locking, is safe because we have mutable access.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.mutex.get_mut()
+    }
+
+    /// Returns the instance of the current thread, which is cloned from the shared instance on first use.
+    pub(super) fn handle(&self) -> &BfSharedMutex<ManuallyDrop<T>> {
+        let handle = HANDLES.with(|handles| {
+            let mut handles = handles.borrow_mut();
+            let token = Arc::as_ptr(&self.token);
+
+            // The weak references keep the tokens allocated, so the address of a token identifies a single lock. The
+            // most recently used lock is kept last, so that repeatedly accessing the same lock needs a single check.
+            if let Some(cached) = handles.last().filter(|cached| Weak::as_ptr(&cached.token) == token) {
+                return cached.handle;
+            }
+
+            // Drop the instances of the locks that no longer exist before searching the others.
+            handles.retain(|cached| cached.token.strong_count() > 0);
+
+            if let Some(index) = handles.iter().position(|cached| Weak::as_ptr(&cached.token) == token) {
+                let last = handles.len() - 1;
+                handles.swap(index, last);
+                return handles[last].handle;
+            }
+
+            let handle = NonNull::from(Box::leak(Box::new(self.mutex.clone()))).cast();
+            handles.push(CachedHandle {
+                token: Arc::downgrade(&self.token),
+                handle,
+                drop: drop_handle::<T>,
+            });
+
+            handle
+        });
+
+        // The instance is only dropped when this thread exits, or after this lock has been dropped.
+        unsafe { handle.cast().as_ref() }
+    }
+}
+
+/// Returns the number of locks for which the current thread caches an instance.
+#[cfg(test)]
+pub(super) fn cached_handles() -> usize {
+    HANDLES.with(|handles| handles.borrow().len())
+}
+
+impl<T> Drop for BfRwLock<T> {
+    fn drop(&mut self) {
+        // No guards exist anymore, but the instances of the threads outlive the lock, so the object is dropped here.
+        unsafe { ManuallyDrop::drop(self.mutex.get_mut()) }
+    }
+}
+
+impl<T: Default> Default for BfRwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl Drop for CachedHandle {
+    fn drop(&mut self) {
+        unsafe { (self.drop)(self.handle) }
+    }
+}
+
+/// Drops the instance of a thread, the object itself has already been dropped when the lock no longer exists.
+unsafe fn drop_handle<T>(handle: NonNull<()>) {
+    drop(Box::from_raw(handle.cast::<BfSharedMutex<ManuallyDrop<T>>>().as_ptr()));
+}
+
+fn map_read<T>(guard: BfSharedMutexReadGuard<'_, ManuallyDrop<T>>) -> BfRwLockReadGuard<'_, T> {
+    BfSharedMutexReadGuard::map(guard, |object| &**object)
+}
+
+fn map_write<T>(guard: BfSharedMutexWriteGuard<'_, ManuallyDrop<T>>) -> BfRwLockWriteGuard<'_, T> {
+    BfSharedMutexWriteGuard::map(guard, |object| &mut **object)
+}
@@ -0,0 +1,20 @@
+use serde::{ser::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::BfSharedMutex;
+
+/// Serializes the object while holding read access, like the implementation for [std::sync::RwLock].
+impl<T: Serialize> Serialize for BfSharedMutex<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.read() {
+            Ok(guard) => guard.serialize(serializer),
+            Err(_) => Err(S::Error::custom("the shared mutex is poisoned")),
+        }
+    }
+}
+
+/// Deserializes the object into a new shared mutex, of which further instances can be cloned.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for BfSharedMutex<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(BfSharedMutex::new)
+    }
+}
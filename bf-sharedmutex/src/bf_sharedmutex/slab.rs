@@ -0,0 +1,144 @@
+use core::ptr::NonNull;
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use crossbeam::utils::CachePadded;
+
+use crate::waiter::lock;
+
+use super::SharedMutexControl;
+
+/// The number of control blocks in the first segment, every following segment is twice as large as the previous one.
+const FIRST_SEGMENT: usize = 8;
+
+/// Stores the control blocks of all instances in a few contiguous, cache-padded segments. The segments are never
+/// moved or freed while the slab exists, so the instances can keep a pointer to their own control block. The slots
+/// in use are kept in a dense list, so that a writer only visits the live instances.
+#[derive(Default)]
+pub(super) struct ControlSlab {
+    segments: Vec<Box<[CachePadded<SharedMutexControl>]>>,
+
+    /// The number of slots that have ever been used, the freed slots below it are reused first.
+    len: usize,
+
+    /// The freed slots, has enough capacity for all slots so that freeing never allocates.
+    free: Vec<usize>,
+
+    /// The slots in use and their control blocks, in no particular order. Has enough capacity for all slots, like the
+    /// free list.
+    live: Vec<(usize, NonNull<CachePadded<SharedMutexControl>>)>,
+
+    /// The position of every slot in the live list, only meaningful for the slots in use.
+    position: Vec<usize>,
+
+    /// Set when the slab consists of a single preallocated segment that never grows.
+    fixed: bool,
+}
+
+impl ControlSlab {
+
+    /// Creates a slab with room for exactly the given number of instances, it never allocates afterwards. Every
+    /// instance is used by a single thread, which parks on its own control block, so the blocks have room for one
+    /// waiter.
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        let control = || {
+            let control = CachePadded::<SharedMutexControl>::default();
+            lock(&control.waiters).reserve_exact(1);
+            control
+        };
+
+        ControlSlab {
+            segments: vec![(0..capacity).map(|_| control()).collect()],
+            len: 0,
+            free: Vec::with_capacity(capacity),
+            live: Vec::with_capacity(capacity),
+            position: Vec::with_capacity(capacity),
+            fixed: true,
+        }
+    }
+
+    /// Takes a slot for a new instance, returns its index and a pointer to the control block that remains valid for
+    /// the lifetime of the slab. Returns None when a fixed capacity slab is full.
+    pub(super) fn insert(&mut self) -> Option<(usize, NonNull<CachePadded<SharedMutexControl>>)> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                if self.len == self.capacity() {
+                    if self.fixed {
+                        return None;
+                    }
+
+                    self.grow();
+                }
+
+                self.position.push(0);
+                self.len += 1;
+                self.len - 1
+            }
+        };
+
+        let control = NonNull::from(self.get(index));
+        self.position[index] = self.live.len();
+        self.live.push((index, control));
+        Some((index, control))
+    }
+
+    /// Frees the slot of a dropped instance, the caller must have reset the control block.
+    pub(super) fn remove(&mut self, index: usize) {
+        debug_assert!(index < self.len && !self.free.contains(&index), "Slot {index} is not in use");
+
+        // Move the last live slot into the position of the removed one.
+        let position = self.position[index];
+        self.live.swap_remove(position);
+        if let Some(&(moved, _)) = self.live.get(position) {
+            self.position[moved] = position;
+        }
+
+        self.free.push(index);
+    }
+
+    /// Returns the control blocks of the slots in use.
+    pub(super) fn iter(&self) -> impl Iterator<Item = &CachePadded<SharedMutexControl>> {
+        // The segments are never moved or freed, so the pointers remain valid.
+        self.live.iter().map(|(_, control)| unsafe { control.as_ref() })
+    }
+
+    /// Returns the number of instances that currently use a slot.
+    pub(super) fn live(&self) -> usize {
+        self.live.len()
+    }
+
+    fn get(&self, index: usize) -> &CachePadded<SharedMutexControl> {
+        if self.fixed {
+            return &self.segments[0][index];
+        }
+
+        // Segment k starts at index FIRST_SEGMENT * (2^k - 1).
+        let segment = (index / FIRST_SEGMENT + 1).ilog2() as usize;
+        &self.segments[segment][index - FIRST_SEGMENT * ((1 << segment) - 1)]
+    }
+
+    /// Returns the number of instances that a fixed capacity slab has room for.
+    pub(super) fn fixed_capacity(&self) -> Option<usize> {
+        self.fixed.then(|| self.capacity())
+    }
+
+    fn capacity(&self) -> usize {
+        if self.fixed {
+            return self.segments[0].len();
+        }
+
+        FIRST_SEGMENT * ((1 << self.segments.len()) - 1)
+    }
+
+    /// Allocates the next segment, and reserves room in the lists for its slots.
+    fn grow(&mut self) {
+        let size = FIRST_SEGMENT << self.segments.len();
+        self.segments.push((0..size).map(|_| CachePadded::default()).collect());
+
+        let capacity = self.capacity();
+        self.free.reserve_exact(capacity - self.free.len());
+        self.live.reserve_exact(capacity - self.live.len());
+        self.position.reserve_exact(capacity - self.position.len());
+    }
+}
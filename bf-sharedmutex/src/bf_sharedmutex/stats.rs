@@ -0,0 +1,143 @@
+#[cfg(feature = "stats")]
+use core::{ops::AddAssign, sync::atomic::{AtomicU64, Ordering}, time::Duration};
+
+#[cfg(feature = "stats")]
+use std::time::Instant;
+
+/// The acquisitions and the contention of a single instance, or of all instances of a shared mutex.
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LockStats {
+    /// The number of read guards, including the nested and upgradable ones.
+    pub reads: u64,
+
+    /// The number of write guards.
+    pub writes: u64,
+
+    /// The number of reads that found their instance forbidden and had to wait for a writer.
+    pub forbidden_reads: u64,
+
+    /// The number of times that a writer checked the busy flag of a reader that was still inside its section.
+    pub writer_spins: u64,
+
+    /// The time that the blocking operations spent waiting, writers include forbidding the instances.
+    pub wait_time: Duration,
+}
+
+/// The statistics returned by [crate::BfSharedMutex::stats].
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BfSharedMutexStats {
+    /// The statistics of the instance on which they were requested.
+    pub handle: LockStats,
+
+    /// The statistics of all instances together, including the ones that have been dropped.
+    pub total: LockStats,
+}
+
+#[cfg(feature = "stats")]
+impl AddAssign for LockStats {
+    fn add_assign(&mut self, other: Self) {
+        self.reads += other.reads;
+        self.writes += other.writes;
+        self.forbidden_reads += other.forbidden_reads;
+        self.writer_spins += other.writer_spins;
+        self.wait_time += other.wait_time;
+    }
+}
+
+/// The counters of a single instance, these are empty and free when the stats feature is disabled.
+#[derive(Default)]
+pub(super) struct Counters {
+    #[cfg(feature = "stats")]
+    reads: AtomicU64,
+    #[cfg(feature = "stats")]
+    writes: AtomicU64,
+    #[cfg(feature = "stats")]
+    forbidden_reads: AtomicU64,
+    #[cfg(feature = "stats")]
+    writer_spins: AtomicU64,
+    #[cfg(feature = "stats")]
+    wait_nanos: AtomicU64,
+}
+
+/// Measures the time that a blocking operation waits.
+#[derive(Clone, Copy)]
+pub(super) struct Stopwatch {
+    #[cfg(feature = "stats")]
+    started: Instant,
+}
+
+impl Stopwatch {
+    #[inline]
+    pub(super) fn start() -> Self {
+        Stopwatch {
+            #[cfg(feature = "stats")]
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Counters {
+    #[inline]
+    pub(super) fn read(&self) {
+        #[cfg(feature = "stats")]
+        increment(&self.reads, 1);
+    }
+
+    #[inline]
+    pub(super) fn write(&self) {
+        #[cfg(feature = "stats")]
+        increment(&self.writes, 1);
+    }
+
+    #[inline]
+    pub(super) fn forbidden_read(&self) {
+        #[cfg(feature = "stats")]
+        increment(&self.forbidden_reads, 1);
+    }
+
+    #[inline]
+    pub(super) fn writer_spin(&self) {
+        #[cfg(feature = "stats")]
+        increment(&self.writer_spins, 1);
+    }
+
+    #[inline]
+    pub(super) fn waited(&self, _stopwatch: Stopwatch) {
+        #[cfg(feature = "stats")]
+        increment(&self.wait_nanos, _stopwatch.started.elapsed().as_nanos().try_into().unwrap_or(u64::MAX));
+    }
+
+    /// Returns the current values of the counters.
+    #[cfg(feature = "stats")]
+    pub(super) fn snapshot(&self) -> LockStats {
+        LockStats {
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            forbidden_reads: self.forbidden_reads.load(Ordering::Relaxed),
+            writer_spins: self.writer_spins.load(Ordering::Relaxed),
+            wait_time: Duration::from_nanos(self.wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Returns the current values and resets the counters, so that the slot can be reused by another instance.
+    #[cfg(feature = "stats")]
+    pub(super) fn take(&self) -> LockStats {
+        LockStats {
+            reads: self.reads.swap(0, Ordering::Relaxed),
+            writes: self.writes.swap(0, Ordering::Relaxed),
+            forbidden_reads: self.forbidden_reads.swap(0, Ordering::Relaxed),
+            writer_spins: self.writer_spins.swap(0, Ordering::Relaxed),
+            wait_time: Duration::from_nanos(self.wait_nanos.swap(0, Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Only the thread that uses the instance updates its counters, so they do not need the more expensive atomic
+/// read-modify-write operations. The atomics only allow the statistics to be read by other instances.
+#[cfg(feature = "stats")]
+#[inline]
+fn increment(counter: &AtomicU64, amount: u64) {
+    counter.store(counter.load(Ordering::Relaxed).saturating_add(amount), Ordering::Relaxed);
+}
@@ -0,0 +1,71 @@
+use std::thread::{self, ThreadId};
+
+use rayon::ThreadPool;
+
+use super::BfSharedMutex;
+
+/// One instance of a [BfSharedMutex] for every worker of a rayon thread pool. These can be shared by the closures of
+/// parallel iterators, which then obtain the instance of the worker that runs them with [BfWorkerHandles::get].
+pub struct BfWorkerHandles<T> {
+    /// The instances indexed by the worker index, together with the thread of the worker.
+    workers: Box<[(ThreadId, BfSharedMutex<T>)]>,
+}
+
+// Every instance is only returned to the worker thread that it belongs to.
+unsafe impl<T: Send + Sync> Sync for BfWorkerHandles<T> {}
+
+thread_local! {
+    static THREAD: ThreadId = thread::current().id();
+}
+
+impl<T> BfSharedMutex<T> {
+
+    /// Registers one instance for every worker of the current rayon thread pool, which is the global pool unless this is
+    /// called inside another pool.
+    pub fn worker_handles(&self) -> BfWorkerHandles<T> {
+        self.handles_for(rayon::broadcast(|_| current_thread()))
+    }
+
+    /// Registers one instance for every worker of the given rayon thread pool.
+    pub fn worker_handles_in(&self, pool: &ThreadPool) -> BfWorkerHandles<T> {
+        self.handles_for(pool.broadcast(|_| current_thread()))
+    }
+
+    /// Clones an instance for every thread, the broadcast returns the threads ordered by their worker index.
+    fn handles_for(&self, threads: Vec<ThreadId>) -> BfWorkerHandles<T> {
+        BfWorkerHandles {
+            workers: threads.into_iter().map(|thread| (thread, self.clone())).collect(),
+        }
+    }
+}
+
+impl<T> BfWorkerHandles<T> {
+
+    /// Returns the instance of the current worker, or None when the current thread is not a worker of the thread pool
+    /// for which these instances were registered.
+    pub fn get(&self) -> Option<&BfSharedMutex<T>> {
+        let (thread, handle) = self.workers.get(rayon::current_thread_index()?)?;
+
+        // Workers of different pools can have the same index, so check that this is the same thread.
+        if *thread == current_thread() {
+            Some(handle)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of workers.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns true iff there are no workers.
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+}
+
+/// Returns the identifier of the current thread, which is cached since [thread::current] clones a reference counter.
+fn current_thread() -> ThreadId {
+    THREAD.with(|thread| *thread)
+}
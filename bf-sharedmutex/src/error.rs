@@ -0,0 +1,195 @@
+use core::fmt::{Debug, Display};
+
+#[cfg(feature = "std")]
+use std::error::Error;
+
+/// The errors that can occur while acquiring access to a [crate::BfSharedMutex]. These
+/// errors do not borrow from the shared mutex, so they can be propagated freely. The
+/// acquisition functions return a [PoisonError] or [TryLockError] that carries the guard
+/// instead, which converts into this error, see [LockResult].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BfSharedMutexError {
+    /// A thread panicked while it had exclusive access to the shared mutex.
+    Poisoned,
+
+    /// The shared mutex could not be acquired without blocking.
+    WouldBlock,
+
+    /// The deadline passed before the shared mutex could be acquired.
+    TimedOut,
+
+    /// No more instances can be registered for the shared mutex.
+    RegistryFull,
+}
+
+impl Display for BfSharedMutexError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BfSharedMutexError::Poisoned => write!(f, "the shared mutex is poisoned"),
+            BfSharedMutexError::WouldBlock => write!(f, "acquiring the shared mutex would block"),
+            BfSharedMutexError::TimedOut => write!(f, "timed out while acquiring the shared mutex"),
+            BfSharedMutexError::RegistryFull => write!(f, "no more instances can be registered for the shared mutex"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for BfSharedMutexError {}
+
+/// The result of the blocking acquisition functions, see [PoisonError].
+///
+/// Like the errors of [std::sync::RwLock] the error carries the guard, so it borrows the shared mutex and is not
+/// `Send`. The `?` operator only applies a single [From] conversion, so to propagate the error into an error type that
+/// implements `From<BfSharedMutexError>` it must first be converted into the `'static` [BfSharedMutexError]:
+///
+/// ```
+/// use bf_sharedmutex::{BfSharedMutex, BfSharedMutexError};
+///
+/// #[derive(Debug)]
+/// enum ServiceError {
+///     Lock(BfSharedMutexError),
+/// }
+///
+/// impl From<BfSharedMutexError> for ServiceError {
+///     fn from(error: BfSharedMutexError) -> Self {
+///         ServiceError::Lock(error)
+///     }
+/// }
+///
+/// fn increment(counter: &BfSharedMutex<u32>) -> Result<u32, ServiceError> {
+///     let mut write = counter.write().map_err(BfSharedMutexError::from)?;
+///     *write += 1;
+///     Ok(*write)
+/// }
+///
+/// assert_eq!(increment(&BfSharedMutex::new(5)).unwrap(), 6);
+/// ```
+pub type LockResult<G> = Result<G, PoisonError<G>>;
+
+/// The result of the non-blocking and timed acquisition functions, see [TryLockError]. Like [LockResult] it is
+/// converted with `map_err(BfSharedMutexError::from)` before it is propagated into other error types.
+pub type TryLockResult<G> = Result<G, TryLockError<G>>;
+
+/// Returned when the shared mutex was acquired, but a thread panicked while it had exclusive
+/// access. Similar to [std::sync::PoisonError] it carries the guard, so that the object can
+/// still be accessed after checking that it is in a consistent state.
+pub struct PoisonError<G> {
+    guard: G,
+}
+
+impl<G> PoisonError<G> {
+
+    /// Constructs a new poison error that carries the given guard.
+    pub fn new(guard: G) -> Self {
+        Self { guard }
+    }
+
+    /// Returns the guard, ignoring the poisoning.
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    /// Returns a reference to the guard.
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    /// Returns a mutable reference to the guard.
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+
+    /// Converts the carried guard, for example into a mapped guard.
+    #[cfg(all(feature = "std", not(loom)))]
+    pub(crate) fn map<H>(self, f: impl FnOnce(G) -> H) -> PoisonError<H> {
+        PoisonError::new(f(self.guard))
+    }
+}
+
+impl<G> Debug for PoisonError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<G> Display for PoisonError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&BfSharedMutexError::Poisoned, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<G> Error for PoisonError<G> {}
+
+/// The errors of the non-blocking and timed acquisition functions.
+pub enum TryLockError<G> {
+    /// The shared mutex was acquired, but it is poisoned.
+    Poisoned(PoisonError<G>),
+
+    /// The shared mutex could not be acquired without blocking.
+    WouldBlock,
+
+    /// The deadline passed before the shared mutex could be acquired.
+    TimedOut,
+}
+
+impl<G> Debug for TryLockError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TryLockError::Poisoned(error) => f.debug_tuple("Poisoned").field(error).finish(),
+            TryLockError::WouldBlock => write!(f, "WouldBlock"),
+            TryLockError::TimedOut => write!(f, "TimedOut"),
+        }
+    }
+}
+
+impl<G> Display for TryLockError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&BfSharedMutexError::from(self), f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<G> Error for TryLockError<G> {}
+
+#[cfg(all(feature = "std", not(loom)))]
+impl<G> TryLockError<G> {
+
+    /// Converts the guard carried by a poison error, see [PoisonError::map].
+    pub(crate) fn map<H>(self, f: impl FnOnce(G) -> H) -> TryLockError<H> {
+        match self {
+            TryLockError::Poisoned(error) => TryLockError::Poisoned(error.map(f)),
+            TryLockError::WouldBlock => TryLockError::WouldBlock,
+            TryLockError::TimedOut => TryLockError::TimedOut,
+        }
+    }
+}
+
+impl<G> From<PoisonError<G>> for TryLockError<G> {
+    fn from(error: PoisonError<G>) -> Self {
+        TryLockError::Poisoned(error)
+    }
+}
+
+impl<G> From<PoisonError<G>> for BfSharedMutexError {
+    fn from(_: PoisonError<G>) -> Self {
+        BfSharedMutexError::Poisoned
+    }
+}
+
+impl<G> From<&TryLockError<G>> for BfSharedMutexError {
+    fn from(error: &TryLockError<G>) -> Self {
+        match error {
+            TryLockError::Poisoned(_) => BfSharedMutexError::Poisoned,
+            TryLockError::WouldBlock => BfSharedMutexError::WouldBlock,
+            TryLockError::TimedOut => BfSharedMutexError::TimedOut,
+        }
+    }
+}
+
+impl<G> From<TryLockError<G>> for BfSharedMutexError {
+    fn from(error: TryLockError<G>) -> Self {
+        BfSharedMutexError::from(&error)
+    }
+}
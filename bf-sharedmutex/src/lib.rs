@@ -1,3 +1,11 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod bf_sharedmutex;
+pub mod error;
+
+mod waiter;
 
-pub use crate::bf_sharedmutex::*;
\ No newline at end of file
+pub use crate::bf_sharedmutex::*;
+pub use crate::error::*;
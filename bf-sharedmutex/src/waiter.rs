@@ -0,0 +1,232 @@
+use core::{sync::atomic::{AtomicBool, Ordering}, task::{Context, Poll, Waker}};
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+pub(crate) use std::time::Instant;
+
+#[cfg(all(not(loom), feature = "std"))]
+use std::{sync::{Mutex, MutexGuard}, thread};
+
+#[cfg(all(not(loom), not(feature = "std")))]
+use spin::{Mutex, MutexGuard};
+
+#[cfg(loom)]
+use loom::{sync::{Mutex, MutexGuard}, thread};
+
+use crossbeam::utils::Backoff;
+
+/// Without std there is no clock, so a deadline can never be given.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy)]
+pub(crate) enum Instant {}
+
+/// A parked thread or an async task that is waiting for the shared mutex.
+pub(crate) enum Waiter {
+    #[cfg(feature = "std")]
+    Thread(thread::Thread),
+    Task(Waker),
+}
+
+impl Waiter {
+
+    /// Returns the waiter for the current thread, which should park itself afterwards.
+    #[cfg(feature = "std")]
+    pub(crate) fn current() -> Self {
+        Waiter::Thread(thread::current())
+    }
+
+    /// Returns true iff both waiters wake up the same thread or task.
+    fn will_wake(&self, other: &Waiter) -> bool {
+        match (self, other) {
+            #[cfg(feature = "std")]
+            (Waiter::Thread(thread), Waiter::Thread(other)) => thread.id() == other.id(),
+            (Waiter::Task(waker), Waiter::Task(other)) => waker.will_wake(other),
+            #[cfg(feature = "std")]
+            _ => false,
+        }
+    }
+
+    /// Wakes up the thread or task, spurious wake ups are harmless since waiters always check their condition again.
+    pub(crate) fn wake(&self) {
+        match self {
+            #[cfg(feature = "std")]
+            Waiter::Thread(thread) => thread.unpark(),
+            Waiter::Task(waker) => waker.wake_by_ref(),
+        }
+    }
+}
+
+/// A lock without data that is held by the writers and the upgradable reader. Unlike the standard mutex it can
+/// be acquired by both threads and async tasks, and it does not have to be released on the acquiring thread.
+#[derive(Default)]
+pub(crate) struct ExclusiveLock {
+    locked: AtomicBool,
+
+    /// All waiters are woken up when the lock is released, the ones that lose the race register themselves again.
+    waiters: Mutex<Vec<Waiter>>,
+}
+
+/// Releases the [ExclusiveLock] when dropped.
+pub(crate) struct ExclusiveGuard<'a> {
+    lock: &'a ExclusiveLock,
+}
+
+impl ExclusiveLock {
+
+    /// Creates a lock with room for the given number of waiters, registering them does not allocate.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        ExclusiveLock {
+            locked: AtomicBool::new(false),
+            waiters: Mutex::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Attempts to acquire the lock without blocking.
+    pub(crate) fn try_lock(&self) -> Option<ExclusiveGuard<'_>> {
+        self.locked.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).ok()?;
+        Some(ExclusiveGuard { lock: self })
+    }
+
+    /// Acquires the lock, spinning for a short while before parking the thread. Returns None when the deadline passed first.
+    pub(crate) fn lock(&self, deadline: Option<Instant>) -> Option<ExclusiveGuard<'_>> {
+        let backoff = Backoff::new();
+        let mut registered = false;
+
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return Some(guard);
+            }
+
+            if expired(deadline) {
+                return None;
+            }
+
+            // Register before trying again, so that the holder is guaranteed to wake us up.
+            snooze(&backoff, &mut registered, deadline, |waiter| register(&mut lock(&self.waiters), waiter));
+        }
+    }
+
+    /// Attempts to acquire the lock, otherwise the task is woken up once the lock has been released.
+    pub(crate) fn poll_lock(&self, cx: &mut Context<'_>) -> Poll<ExclusiveGuard<'_>> {
+        if let Some(guard) = self.try_lock() {
+            return Poll::Ready(guard);
+        }
+
+        register(&mut lock(&self.waiters), Waiter::Task(cx.waker().clone()));
+        match self.try_lock() {
+            Some(guard) => Poll::Ready(guard),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Registers a waiter that is woken up once the lock has been released.
+    #[cfg(all(feature = "std", not(loom)))]
+    pub(crate) fn register(&self, waiter: Waiter) {
+        register(&mut lock(&self.waiters), waiter);
+    }
+
+    /// Constructs a guard for the lock again. The caller must hold the lock, of which the guard has been forgotten.
+    pub(crate) unsafe fn guard(&self) -> ExclusiveGuard<'_> {
+        ExclusiveGuard { lock: self }
+    }
+}
+
+impl Drop for ExclusiveGuard<'_> {
+    fn drop(&mut self) {
+        // We hold the lock, since the guard is only constructed after acquiring it.
+        unsafe { self.lock.force_unlock() };
+    }
+}
+
+impl ExclusiveLock {
+
+    /// Releases the lock and wakes up the waiters. The caller must hold the lock, of which the guard has been forgotten.
+    pub(crate) unsafe fn force_unlock(&self) {
+        self.locked.store(false, Ordering::SeqCst);
+
+        // The list is drained in place, so that its capacity is kept and a fixed capacity lock never allocates.
+        for waiter in lock(&self.waiters).drain(..) {
+            waiter.wake();
+        }
+    }
+}
+
+/// Adds the waiter to the list, or replaces the entry of the same thread or task. A future that is polled again while
+/// it waits then keeps a single entry, instead of adding one for every poll.
+pub(crate) fn register(waiters: &mut Vec<Waiter>, waiter: Waiter) {
+    match waiters.iter_mut().find(|registered| registered.will_wake(&waiter)) {
+        Some(registered) => *registered = waiter,
+        None => waiters.push(waiter),
+    }
+}
+
+/// Waits a little before the caller checks its condition again. After spinning for a short while the current thread
+/// is first registered, so that it is guaranteed to be woken up once the condition changes, and then parked on the
+/// next call. Without std the thread cannot be parked, so it keeps spinning instead.
+pub(crate) fn snooze(backoff: &Backoff, registered: &mut bool, deadline: Option<Instant>, register: impl FnOnce(Waiter)) {
+    #[cfg(feature = "std")]
+    if backoff.is_completed() {
+        if !*registered {
+            register(Waiter::current());
+            *registered = true;
+        } else {
+            // The waiters can be removed when they are woken up, so register again before parking the next time.
+            park_until(deadline);
+            *registered = false;
+        }
+
+        return;
+    }
+
+    #[cfg(not(feature = "std"))]
+    let _ = (registered, deadline, register);
+
+    backoff.snooze();
+}
+
+/// Returns true iff the deadline has passed.
+pub(crate) fn expired(deadline: Option<Instant>) -> bool {
+    #[cfg(feature = "std")]
+    return deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+    #[cfg(not(feature = "std"))]
+    match deadline {
+        Some(deadline) => match deadline {},
+        None => false,
+    }
+}
+
+/// Returns true iff the current thread is unwinding, which is never the case without std.
+pub(crate) fn panicking() -> bool {
+    #[cfg(feature = "std")]
+    return std::thread::panicking();
+
+    #[cfg(not(feature = "std"))]
+    false
+}
+
+/// Locks one of the internal mutexes. Their data is never left in an inconsistent state and poisoning
+/// of the object is tracked separately, so the poisoning of these mutexes can be ignored.
+#[cfg(any(loom, feature = "std"))]
+pub(crate) fn lock<U>(mutex: &Mutex<U>) -> MutexGuard<'_, U> {
+    mutex.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Locks one of the internal spin locks, which are only held for short periods.
+#[cfg(all(not(loom), not(feature = "std")))]
+pub(crate) fn lock<U>(mutex: &Mutex<U>) -> MutexGuard<'_, U> {
+    mutex.lock()
+}
+
+/// Parks the current thread until it is unparked, or the deadline has passed.
+#[cfg(feature = "std")]
+fn park_until(deadline: Option<Instant>) {
+    match deadline {
+        #[cfg(not(loom))]
+        Some(deadline) => thread::park_timeout(deadline.saturating_duration_since(Instant::now())),
+        #[cfg(loom)]
+        Some(_) => thread::yield_now(),
+        None => thread::park(),
+    }
+}
@@ -158,6 +158,9 @@ impl<T> BfVecShared<T> {
                 ptr::drop_in_place(ptr);
             }
         }
+
+        // The elements have been dropped, so they must not be visited again.
+        self.len.store(0, Ordering::Relaxed);
     }
 }
 
@@ -177,10 +180,44 @@ impl<T> Drop for BfVecShared<T> {
 
 unsafe impl<T> Send for BfVec<T> {}
 
+/// Serializes the elements as a sequence. Pushes write their element while holding read access, so the elements are
+/// only guaranteed to be initialized while holding write access.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for BfVec<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let Ok(write) = self.shared.write() else {
+            return Err(S::Error::custom("the shared mutex is poisoned"));
+        };
+
+        let len = write.len.load(Ordering::Relaxed);
+        match write.buffer {
+            Some(buffer) => serializer.collect_seq(unsafe { std::slice::from_raw_parts(buffer.as_ptr(), len) }),
+            None => serializer.collect_seq(std::iter::empty::<T>()),
+        }
+    }
+}
+
+/// Deserializes the elements into a new vector, of which further views can be shared.
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for BfVec<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let elements = Vec::<T>::deserialize(deserializer)?;
+
+        let vector = BfVec::new();
+        for element in elements {
+            vector.push(element);
+        }
+
+        Ok(vector)
+    }
+}
+
 #[cfg(test)]
 #[cfg(not(loom))]
 mod tests {
-    use std::thread;
+    use std::{sync::Arc, thread};
 
     use super::*;
 
@@ -216,4 +253,50 @@ mod tests {
         assert_eq!(total, num_threads * (num_threads - 1) * num_iterations / 2);
         assert_eq!(shared_vector.len(), (num_threads * num_iterations) as usize);
     }
+
+    #[test]
+    fn test_clear() {
+        let object = Arc::new(());
+        let shared_vector = BfVec::new();
+        for _ in 0..10 {
+            shared_vector.push(object.clone());
+        }
+        assert_eq!(Arc::strong_count(&object), 11);
+
+        shared_vector.clear();
+        assert_eq!(shared_vector.len(), 0);
+        assert_eq!(Arc::strong_count(&object), 1);
+
+        // The cleared elements must not be dropped again with the vector.
+        drop(shared_vector);
+        assert_eq!(Arc::strong_count(&object), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let shared_vector = BfVec::new();
+        assert_eq!(serde_json::to_string(&shared_vector).unwrap(), "[]");
+
+        for i in 0..10 {
+            shared_vector.push(i.to_string());
+        }
+
+        let json = serde_json::to_string(&shared_vector).unwrap();
+        let deserialized: BfVec<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.len(), 10);
+        assert_eq!(deserialized[9], "9");
+
+        shared_vector.clear();
+        assert_eq!(serde_json::to_string(&shared_vector).unwrap(), "[]");
+
+        // A poisoned vector fails to serialize instead of panicking.
+        let other = shared_vector.share();
+        thread::spawn(move || {
+            let _write = other.shared.write().unwrap();
+            panic!("Poison the shared mutex");
+        }).join().unwrap_err();
+
+        assert!(serde_json::to_string(&shared_vector).is_err());
+    }
 }